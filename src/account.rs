@@ -0,0 +1,331 @@
+use crate::error::{Error, Result};
+use crate::trade::Trade;
+use ibapi::accounts::{AccountPortfolioValue, AccountSummaries, AccountSummaryTags, AccountUpdate};
+use ibapi::client::Subscription;
+use ibapi::Client;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How IB splits an order placed for an FA allocation group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaMethod {
+    EqualQuantity,
+    NetLiq,
+    AvailableEquity,
+    PctChange,
+}
+
+impl fmt::Display for FaMethod {
+    /// The name TWS expects in `Order::fa_method`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FaMethod::EqualQuantity => "EqualQuantity",
+            FaMethod::NetLiq => "NetLiq",
+            FaMethod::AvailableEquity => "AvailableEquity",
+            FaMethod::PctChange => "PctChange",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for FaMethod {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().replace('_', "").as_str() {
+            "equalquantity" => Ok(FaMethod::EqualQuantity),
+            "netliq" => Ok(FaMethod::NetLiq),
+            "availableequity" => Ok(FaMethod::AvailableEquity),
+            "pctchange" => Ok(FaMethod::PctChange),
+            other => Err(Error::Config(format!("Unknown FA method: {}", other))),
+        }
+    }
+}
+
+/// Picks the account to trade: `wanted` if TWS manages it, else the first
+/// managed account.
+pub fn select_account(managed: &[String], wanted: Option<&str>) -> Result<String> {
+    match wanted {
+        Some(wanted) if managed.iter().any(|account| account == wanted) => Ok(wanted.to_string()),
+        Some(wanted) => Err(Error::Config(format!(
+            "Account {} is not managed by this login (managed: {})",
+            wanted,
+            managed.join(", ")
+        ))),
+        None => managed
+            .first()
+            .cloned()
+            .ok_or_else(|| Error::Connection("TWS reported no managed accounts".to_string())),
+    }
+}
+
+/// IB's paper accounts start with `DU`, or `DF` for a paper FA master.
+pub fn is_paper_account(account: &str) -> bool {
+    account.starts_with("DU") || account.starts_with("DF")
+}
+
+/// Refuses to trade `account` when it isn't the kind `--paper`/`--live`
+/// (or `PAPER_TRADING`) asked for, so a live login is never traded by
+/// mistake.
+pub fn check_trading_mode(account: &str, paper_trading: bool) -> Result<()> {
+    match (paper_trading, is_paper_account(account)) {
+        (true, false) => Err(Error::Config(format!(
+            "Account {} is a live account but paper trading is selected; pass --live to trade it",
+            account
+        ))),
+        (false, true) => Err(Error::Config(format!(
+            "Account {} is a paper account but live trading is selected; pass --paper to trade it",
+            account
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Account summary tags shown in the account view.
+pub const SUMMARY_TAGS: &[&str] = &[
+    AccountSummaryTags::NET_LIQUIDATION,
+    AccountSummaryTags::TOTAL_CASH_VALUE,
+    AccountSummaryTags::BUYING_POWER,
+    AccountSummaryTags::MAINT_MARGIN_REQ,
+];
+
+/// Balances from IB's account summary. Values are `None` until IB reports
+/// them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AccountSummary {
+    pub account: String,
+    pub currency: String,
+    pub net_liquidation: Option<f64>,
+    pub cash: Option<f64>,
+    pub buying_power: Option<f64>,
+    pub maintenance_margin: Option<f64>,
+}
+
+impl AccountSummary {
+    /// Records one tag. Account summary tags and account update keys share
+    /// names, so both feeds go through here; other tags are ignored.
+    pub fn apply(&mut self, account: &str, tag: &str, value: &str, currency: &str) {
+        let field = match tag {
+            AccountSummaryTags::NET_LIQUIDATION => &mut self.net_liquidation,
+            AccountSummaryTags::TOTAL_CASH_VALUE => &mut self.cash,
+            AccountSummaryTags::BUYING_POWER => &mut self.buying_power,
+            AccountSummaryTags::MAINT_MARGIN_REQ => &mut self.maintenance_margin,
+            _ => return,
+        };
+        let Ok(value) = value.parse::<f64>() else {
+            tracing::warn!("{}: invalid {} value {:?}", account, tag, value);
+            return;
+        };
+        *field = Some(value);
+        self.account = account.to_string();
+        self.currency = currency.to_string();
+    }
+}
+
+/// One position as IB values it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PortfolioPosition {
+    pub symbol: String,
+    pub contract_id: i32,
+    pub position: f64,
+    pub market_price: f64,
+    pub market_value: f64,
+    pub average_cost: f64,
+    pub unrealized_pnl: f64,
+    pub realized_pnl: f64,
+}
+
+impl From<&AccountPortfolioValue> for PortfolioPosition {
+    fn from(value: &AccountPortfolioValue) -> Self {
+        PortfolioPosition {
+            symbol: value.contract.symbol.clone(),
+            contract_id: value.contract.contract_id,
+            position: value.position,
+            market_price: value.market_price,
+            market_value: value.market_value,
+            average_cost: value.average_cost,
+            unrealized_pnl: value.unrealized_pnl,
+            realized_pnl: value.realized_pnl,
+        }
+    }
+}
+
+/// Balances and portfolio of one account.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Account {
+    pub summary: AccountSummary,
+    pub positions: Vec<PortfolioPosition>,
+}
+
+impl Account {
+    pub fn new(account_id: &str) -> Self {
+        Account {
+            summary: AccountSummary {
+                account: account_id.to_string(),
+                ..Default::default()
+            },
+            positions: Vec::new(),
+        }
+    }
+
+    /// Records a summary tag for `account_id`. The summary request covers
+    /// every managed account, so other accounts' tags are skipped.
+    pub fn apply_summary(&mut self, account_id: &str, update: &AccountSummaries) {
+        match update {
+            AccountSummaries::Summary(summary) if summary.account == account_id => {
+                self.summary.apply(
+                    &summary.account,
+                    &summary.tag,
+                    &summary.value,
+                    &summary.currency,
+                )
+            }
+            _ => {}
+        }
+    }
+
+    /// Records a portfolio update. Closed positions are dropped.
+    pub fn apply_update(&mut self, update: &AccountUpdate) {
+        match update {
+            AccountUpdate::AccountValue(value) => {
+                let account = value.account.as_deref().unwrap_or(&self.summary.account);
+                let account = account.to_string();
+                self.summary
+                    .apply(&account, &value.key, &value.value, &value.currency);
+            }
+            AccountUpdate::PortfolioValue(value) => {
+                let position = PortfolioPosition::from(value);
+                let existing = self
+                    .positions
+                    .iter()
+                    .position(|held| held.contract_id == position.contract_id);
+                match (existing, position.position == 0.0) {
+                    (Some(index), true) => {
+                        self.positions.remove(index);
+                    }
+                    (Some(index), false) => self.positions[index] = position,
+                    (None, true) => {}
+                    (None, false) => self.positions.push(position),
+                }
+            }
+            AccountUpdate::UpdateTime(_) | AccountUpdate::End => {}
+        }
+    }
+
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.positions
+            .iter()
+            .map(|position| position.unrealized_pnl)
+            .sum()
+    }
+
+    /// One-off read of an account's summary and portfolio.
+    pub fn fetch(client: &Client, account_id: &str) -> Result<Self> {
+        let mut account = Account::new(account_id);
+        let summary = client.account_summary("All", SUMMARY_TAGS)?;
+        for update in &summary {
+            if matches!(update, AccountSummaries::End) {
+                break;
+            }
+            account.apply_summary(account_id, &update);
+        }
+        summary.cancel();
+
+        let updates = client.account_updates(account_id)?;
+        for update in &updates {
+            if matches!(update, AccountUpdate::End) {
+                break;
+            }
+            account.apply_update(&update);
+        }
+        updates.cancel();
+        Ok(account)
+    }
+}
+
+/// Live account feeds for the dashboard, polled without blocking.
+pub struct AccountFeed<'a> {
+    account_id: String,
+    summary: Subscription<'a, AccountSummaries>,
+    updates: Subscription<'a, AccountUpdate>,
+    pub account: Account,
+}
+
+impl<'a> AccountFeed<'a> {
+    pub fn subscribe(client: &'a Client, account_id: &str) -> Result<Self> {
+        Ok(AccountFeed {
+            account_id: account_id.to_string(),
+            summary: client.account_summary("All", SUMMARY_TAGS)?,
+            updates: client.account_updates(account_id)?,
+            account: Account::new(account_id),
+        })
+    }
+
+    pub fn poll(&mut self) {
+        while let Some(update) = self.summary.try_next() {
+            self.account.apply_summary(&self.account_id, &update);
+        }
+        while let Some(update) = self.updates.try_next() {
+            self.account.apply_update(&update);
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.summary.cancel();
+        self.updates.cancel();
+    }
+}
+
+/// IB's unrealized PnL for a held trade next to `Trade::calculate_pnl` at
+/// IB's market price. IB's average cost includes commissions, so small
+/// differences are expected.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PnlCheck {
+    pub symbol: String,
+    pub ib_unrealized_pnl: f64,
+    pub local_unrealized_pnl: f64,
+    pub difference: f64,
+}
+
+impl PnlCheck {
+    pub fn is_mismatch(&self, tolerance: f64) -> bool {
+        self.difference.abs() > tolerance
+    }
+}
+
+/// Compares every trade of the account holding a position with IB's
+/// portfolio entry for its contract. Trades IB has no position for are
+/// skipped; recovery reports those.
+pub fn reconcile(account: &Account, trades: &[Trade]) -> Vec<PnlCheck> {
+    let positions = &account.positions;
+    trades
+        .iter()
+        .filter(|trade| trade.position != 0)
+        .filter(|trade| {
+            trade
+                .account
+                .as_ref()
+                .is_none_or(|id| *id == account.summary.account)
+        })
+        .filter_map(|trade| {
+            let position = positions.iter().find(|position| {
+                if trade.contract_id != 0 {
+                    position.contract_id as i64 == trade.contract_id
+                } else {
+                    position.symbol == trade.symbol
+                }
+            })?;
+            let mut local = trade.clone();
+            local.current_price = position.market_price;
+            let local_unrealized_pnl = local.calculate_pnl();
+            Some(PnlCheck {
+                symbol: trade.symbol.clone(),
+                ib_unrealized_pnl: position.unrealized_pnl,
+                local_unrealized_pnl,
+                difference: position.unrealized_pnl - local_unrealized_pnl,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,168 @@
+use crate::export::CompletedTrade;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+/// Win/loss statistics over a set of completed trades. PnL is net of
+/// commissions throughout.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TradeStats {
+    pub trades: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub win_rate: f64,
+    pub average_win: f64,
+    pub average_loss: f64,
+    /// Gross wins over gross losses, `None` when there are no losses.
+    pub profit_factor: Option<f64>,
+    /// Average PnL per trade.
+    pub expectancy: f64,
+    pub total_pnl: f64,
+    pub average_hold_seconds: f64,
+}
+
+impl TradeStats {
+    pub fn compute(trades: &[CompletedTrade]) -> Self {
+        if trades.is_empty() {
+            return TradeStats::default();
+        }
+        let pnl: Vec<f64> = trades.iter().map(CompletedTrade::net_pnl).collect();
+        let gross_win: f64 = pnl.iter().filter(|pnl| **pnl > 0.0).sum();
+        let gross_loss: f64 = pnl.iter().filter(|pnl| **pnl < 0.0).sum();
+        let wins = pnl.iter().filter(|pnl| **pnl > 0.0).count();
+        let losses = pnl.iter().filter(|pnl| **pnl < 0.0).count();
+        let total_pnl: f64 = pnl.iter().sum();
+        let hold_seconds: i64 = trades.iter().map(CompletedTrade::hold_seconds).sum();
+
+        TradeStats {
+            trades: trades.len(),
+            wins,
+            losses,
+            win_rate: wins as f64 / trades.len() as f64,
+            average_win: if wins > 0 {
+                gross_win / wins as f64
+            } else {
+                0.0
+            },
+            average_loss: if losses > 0 {
+                gross_loss / losses as f64
+            } else {
+                0.0
+            },
+            profit_factor: (gross_loss < 0.0).then(|| gross_win / -gross_loss),
+            expectancy: total_pnl / trades.len() as f64,
+            total_pnl,
+            average_hold_seconds: hold_seconds as f64 / trades.len() as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolStats {
+    pub symbol: String,
+    #[serde(flatten)]
+    pub stats: TradeStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyPnl {
+    pub date: NaiveDate,
+    pub pnl: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    #[serde(flatten)]
+    pub stats: TradeStats,
+    /// Largest peak-to-trough decline of cumulative PnL, in dollars.
+    pub max_drawdown: f64,
+    /// Annualized from daily PnL, `None` with fewer than two trading days.
+    pub sharpe: Option<f64>,
+    pub sortino: Option<f64>,
+    pub daily: Vec<DailyPnl>,
+    pub by_symbol: Vec<SymbolStats>,
+}
+
+impl PerformanceReport {
+    pub fn compute(trades: &[CompletedTrade]) -> Self {
+        let mut sorted: Vec<&CompletedTrade> = trades.iter().collect();
+        sorted.sort_by_key(|trade| trade.closed_at());
+
+        let mut symbols: BTreeMap<&str, Vec<CompletedTrade>> = BTreeMap::new();
+        for trade in trades {
+            symbols
+                .entry(trade.record.symbol.as_str())
+                .or_default()
+                .push(trade.clone());
+        }
+
+        let daily = daily_pnl(trades);
+        let returns: Vec<f64> = daily.iter().map(|day| day.pnl).collect();
+
+        PerformanceReport {
+            stats: TradeStats::compute(trades),
+            max_drawdown: max_drawdown(sorted.iter().map(|trade| trade.net_pnl())),
+            sharpe: sharpe_ratio(&returns),
+            sortino: sortino_ratio(&returns),
+            daily,
+            by_symbol: symbols
+                .into_iter()
+                .map(|(symbol, trades)| SymbolStats {
+                    symbol: symbol.to_string(),
+                    stats: TradeStats::compute(&trades),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Net PnL summed by close date (UTC), in date order.
+pub fn daily_pnl(trades: &[CompletedTrade]) -> Vec<DailyPnl> {
+    let mut days: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+    for trade in trades {
+        *days.entry(trade.closed_at().date_naive()).or_default() += trade.net_pnl();
+    }
+    days.into_iter()
+        .map(|(date, pnl)| DailyPnl { date, pnl })
+        .collect()
+}
+
+pub fn max_drawdown(pnl: impl IntoIterator<Item = f64>) -> f64 {
+    let mut equity = 0.0;
+    let mut peak = 0.0_f64;
+    let mut drawdown = 0.0_f64;
+    for pnl in pnl {
+        equity += pnl;
+        peak = peak.max(equity);
+        drawdown = drawdown.max(peak - equity);
+    }
+    drawdown
+}
+
+pub fn sharpe_ratio(daily: &[f64]) -> Option<f64> {
+    if daily.len() < 2 {
+        return None;
+    }
+    let mean = mean(daily);
+    let variance =
+        daily.iter().map(|pnl| (pnl - mean).powi(2)).sum::<f64>() / (daily.len() - 1) as f64;
+    let deviation = variance.sqrt();
+    (deviation > 0.0).then(|| mean / deviation * TRADING_DAYS_PER_YEAR.sqrt())
+}
+
+/// Like Sharpe, but only losing days count towards the deviation.
+pub fn sortino_ratio(daily: &[f64]) -> Option<f64> {
+    if daily.len() < 2 {
+        return None;
+    }
+    let mean = mean(daily);
+    let downside = daily.iter().map(|pnl| pnl.min(0.0).powi(2)).sum::<f64>() / daily.len() as f64;
+    let deviation = downside.sqrt();
+    (deviation > 0.0).then(|| mean / deviation * TRADING_DAYS_PER_YEAR.sqrt())
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
@@ -0,0 +1,1261 @@
+use crate::account::{self, AccountFeed};
+use crate::audit::AuditLog;
+use crate::bars::{self, BarBuilder, BarBus, BarEvent, BarSource};
+use crate::chart;
+use crate::config::Config;
+use crate::depth::{self, DepthFeed};
+use crate::error::{Error, Result};
+use crate::excursion::Excursion;
+use crate::history::Bar;
+use crate::indicators::{Atr, Indicator, Vwap};
+use crate::journal::{self, Fill, Journal};
+use crate::kill_switch;
+use crate::orders::OrderManager;
+use crate::pnl::{self, Commissions, PnlBreakdown, PnlFeed};
+use crate::sizing::{self, SizingMode};
+use crate::trade::{Stage, Trade};
+use crate::ui::{self, format_money, Dashboard, Terminal, WatchRow};
+use chrono::NaiveDateTime;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ibapi::client::Subscription;
+use ibapi::contracts::tick_types::TickType;
+use ibapi::market_data::realtime::{self, TickTypes};
+use ibapi::orders::{Action, OrderStatus, OrderUpdate, PlaceOrder};
+use ibapi::Client;
+use serde_json::json;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long to wait for a key press before checking for new ticks.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An order sent from the dashboard, followed until it is filled and its
+/// commissions are in, or until it is cancelled.
+struct WorkingOrder<'a> {
+    order_id: i32,
+    action: Action,
+    /// `None` for an order placed before a restart, whose updates come
+    /// from the shared order update stream instead.
+    subscription: Option<Subscription<'a, PlaceOrder>>,
+    recorded: bool,
+    filled: bool,
+    cancelled: bool,
+    fills: Vec<Fill>,
+}
+
+impl WorkingOrder<'_> {
+    /// An order recovered from the journal, still working at IB.
+    fn recovered(order_id: i32, action: Action) -> Self {
+        WorkingOrder {
+            order_id,
+            action,
+            subscription: None,
+            recorded: true,
+            filled: false,
+            cancelled: false,
+            fills: Vec::new(),
+        }
+    }
+
+    /// Whether an update from the shared order stream is about this order.
+    fn owns(&self, event: &PlaceOrder) -> bool {
+        match event {
+            PlaceOrder::OpenOrder(data) => data.order_id == self.order_id,
+            PlaceOrder::OrderStatus(status) => status.order_id == self.order_id,
+            PlaceOrder::ExecutionData(data) => data.execution.order_id == self.order_id,
+            PlaceOrder::CommissionReport(report) => self
+                .fills
+                .iter()
+                .any(|fill| fill.execution_id == report.execution_id),
+            PlaceOrder::Message(_) => false,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.cancelled || (self.filled && self.fills.iter().all(|fill| fill.commission.is_some()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shutdown {
+    Running,
+    /// Asking whether to flatten, leave positions open or keep trading.
+    Confirming,
+    /// Exits sent, waiting for them to fill.
+    Flattening,
+    Exit,
+}
+
+/// One watchlist symbol: its live trade, quote and bar streams,
+/// indicators and working order.
+struct Row<'a> {
+    trade: Trade,
+    market_data: Subscription<'a, TickTypes>,
+    realtime_bars: Option<Subscription<'a, realtime::Bar>>,
+    builders: Vec<BarBuilder>,
+    /// Completed bars of the first interval, for the chart.
+    history: VecDeque<Bar>,
+    vwap: Vwap,
+    /// Start of the regular session the VWAP covers, so it restarts at
+    /// the next open.
+    vwap_session: Option<NaiveDateTime>,
+    atr: Atr,
+    working: Option<WorkingOrder<'a>>,
+    /// The protective stop sent when the entry filled, followed like
+    /// `working` so a triggered stop closes the trade.
+    stop: Option<WorkingOrder<'a>>,
+    /// Orders cancelled to make way for an exit, followed until IB
+    /// confirms them.
+    cancelled: Vec<WorkingOrder<'a>>,
+}
+
+/// Interactive trading session: streams quotes for every watchlist symbol
+/// into the dashboard and turns answers on the selected row into orders.
+pub struct App<'a> {
+    client: &'a Client,
+    config: &'a Config,
+    journal: &'a Journal,
+    audit: AuditLog,
+    orders: OrderManager<'a>,
+    account: AccountFeed<'a>,
+    show_account: bool,
+    show_chart: bool,
+    show_depth: bool,
+    /// Answer being typed at the sizing prompt, while it is open.
+    sizing_input: Option<String>,
+    depth: Option<DepthFeed<'a>>,
+    /// Row the depth feed follows, set even when subscribing failed so it
+    /// isn't retried every frame.
+    depth_row: Option<usize>,
+    /// Index into `chart::WINDOWS`.
+    chart_zoom: usize,
+    pnl: PnlFeed<'a>,
+    /// Keys of the PnL discrepancies already warned about, forgotten once
+    /// IB and the local books agree again.
+    pnl_warnings: HashSet<String>,
+    /// Journaled fills of each trade by journal id, for the commissions
+    /// taken off the local PnL.
+    fills: HashMap<i64, Vec<Fill>>,
+    /// Exits sent when the daily loss limit tripped, while still working.
+    loss_exits: Vec<i32>,
+    /// Updates for every order, opened when recovered trades left entry
+    /// or stop orders working before the restart.
+    order_updates: Option<Subscription<'a, OrderUpdate>>,
+    rows: Vec<Row<'a>>,
+    bars: BarBus,
+    /// Round trips completed this session, kept for the portfolio PnL.
+    closed: Vec<Trade>,
+    selected: usize,
+    shutdown: Shutdown,
+    dashboard: Dashboard,
+}
+
+impl<'a> App<'a> {
+    /// Subscribes to quotes and bars for each trade, to the account's
+    /// balances and portfolio, and to IB's PnL for the account and each
+    /// contract. Every trade needs its contract.
+    pub fn new(
+        client: &'a Client,
+        config: &'a Config,
+        journal: &'a Journal,
+        account: &str,
+        trades: Vec<Trade>,
+    ) -> Result<Self> {
+        if trades.is_empty() {
+            return Err(Error::Other("Watchlist is empty".to_string()));
+        }
+        let mut pnl = PnlFeed::subscribe(client, account)?;
+        let mut fills = HashMap::new();
+        let mut rows = Vec::new();
+        for trade in trades {
+            pnl.watch(trade.contract_id)?;
+            if let Some(id) = trade.journal_id {
+                fills.insert(id, journal.fills(id)?);
+            }
+            let contract = trade.contract.as_ref().ok_or_else(|| {
+                Error::MarketData(format!("{}: contract not created", trade.symbol))
+            })?;
+            let market_data = client.market_data(contract, &[], false, false)?;
+            let realtime_bars = if config.realtime_bars {
+                Some(bars::subscribe_realtime(
+                    client,
+                    contract,
+                    !config.outside_rth,
+                )?)
+            } else {
+                None
+            };
+            let working = trade
+                .entry_order_id
+                .map(|order_id| WorkingOrder::recovered(order_id, Action::Buy));
+            let exit = if trade.position < 0 {
+                Action::Buy
+            } else {
+                Action::Sell
+            };
+            let stop = trade
+                .stop_order_id
+                .map(|order_id| WorkingOrder::recovered(order_id, exit));
+            rows.push(Row {
+                trade,
+                market_data,
+                realtime_bars,
+                builders: config
+                    .bar_intervals
+                    .iter()
+                    .map(|interval| BarBuilder::new(*interval))
+                    .collect(),
+                history: VecDeque::new(),
+                vwap: Vwap::new(),
+                vwap_session: None,
+                atr: Atr::new(config.atr_period),
+                working,
+                stop,
+                cancelled: Vec::new(),
+            });
+        }
+
+        let recovered = rows
+            .iter()
+            .any(|row| row.working.is_some() || row.stop.is_some());
+        let order_updates = if recovered {
+            Some(client.order_update_stream()?)
+        } else {
+            None
+        };
+
+        Ok(App {
+            client,
+            config,
+            journal,
+            audit: AuditLog::open(&config.audit_log_path)?,
+            orders: OrderManager::new(client, config)?,
+            account: AccountFeed::subscribe(client, account)?,
+            show_account: false,
+            show_chart: false,
+            show_depth: false,
+            sizing_input: None,
+            depth: None,
+            depth_row: None,
+            chart_zoom: 1,
+            pnl,
+            pnl_warnings: HashSet::new(),
+            fills,
+            loss_exits: Vec::new(),
+            order_updates,
+            rows,
+            bars: BarBus::new(),
+            closed: Vec::new(),
+            selected: 0,
+            shutdown: Shutdown::Running,
+            dashboard: Dashboard::new(),
+        })
+    }
+
+    pub fn dashboard(&mut self) -> &mut Dashboard {
+        &mut self.dashboard
+    }
+
+    /// Completed bars of every symbol, from IB's real-time bars and the
+    /// configured `bar_intervals`.
+    pub fn bars(&mut self) -> &mut BarBus {
+        &mut self.bars
+    }
+
+    /// Every trade of the session: live rows first, then completed round trips.
+    fn trades(&self) -> Vec<Trade> {
+        self.rows
+            .iter()
+            .map(|row| row.trade.clone())
+            .chain(self.closed.iter().cloned())
+            .collect()
+    }
+
+    /// Runs the dashboard until the user quits or a SIGINT/SIGTERM arrives,
+    /// then moves the live trades to `Stage::Disconnect` and returns every
+    /// trade of the session.
+    pub fn run(mut self) -> Result<Vec<Trade>> {
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let mut signals = Vec::new();
+        for signal in [SIGINT, SIGTERM] {
+            signals.push(signal_hook::flag::register(
+                signal,
+                Arc::clone(&interrupted),
+            )?);
+        }
+        let mut terminal = Terminal::enter()?;
+
+        while self.shutdown != Shutdown::Exit {
+            if interrupted.swap(false, Ordering::Relaxed) {
+                self.request_shutdown();
+            }
+            for index in 0..self.rows.len() {
+                while let Some(tick) = self.rows[index].market_data.try_next() {
+                    self.on_tick(index, tick)?;
+                }
+                self.poll_order(index)?;
+                self.poll_bars(index);
+            }
+            self.poll_order_updates()?;
+            self.poll_depth();
+            self.account.poll();
+            if let Some(update) = self.pnl.poll() {
+                self.orders.kill_switch().update_account_pnl(&update);
+            }
+            self.enforce_loss_limit()?;
+            self.check_pnl();
+            if self.shutdown == Shutdown::Flattening && !self.has_exposure() {
+                self.shutdown = Shutdown::Exit;
+                continue;
+            }
+            let portfolio_pnl = kill_switch::session_pnl(&self.trades());
+            let mut panel = Vec::new();
+            if self.show_account {
+                panel.extend(self.account_panel());
+            }
+            if self.show_depth {
+                panel.extend(self.depth_panel());
+            }
+            if self.show_chart {
+                panel.extend(self.chart_panel());
+            }
+            self.dashboard.set_panel(panel);
+            let rows: Vec<WatchRow> = self
+                .rows
+                .iter()
+                .map(|row| WatchRow {
+                    trade: &row.trade,
+                    order_working: row.working.is_some(),
+                    vwap: row.vwap.value(),
+                    atr: row.atr.value(),
+                })
+                .collect();
+            terminal.draw(&self.dashboard.frame(&rows, self.selected, portfolio_pnl))?;
+
+            if !event::poll(POLL_INTERVAL)? {
+                continue;
+            }
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => self.on_key(key),
+                Event::Resize(..) => terminal.invalidate()?,
+                _ => {}
+            }
+        }
+
+        drop(terminal);
+        for signal in signals {
+            signal_hook::low_level::unregister(signal);
+        }
+        self.disconnect()?;
+        Ok(self.trades())
+    }
+
+    fn account_panel(&self) -> Vec<ui::Line> {
+        let account = &self.account.account;
+        let trades: Vec<Trade> = self.rows.iter().map(|row| row.trade.clone()).collect();
+        let checks = account::reconcile(account, &trades);
+        ui::account_lines(account, &checks, self.config.pnl_tolerance)
+    }
+
+    /// Follows the selected row with a depth subscription while the depth
+    /// panel is shown, and applies its updates. Hiding the panel cancels it.
+    fn poll_depth(&mut self) {
+        if self.config.depth_rows == 0 || !self.show_depth {
+            if let Some(depth) = self.depth.take() {
+                depth.cancel();
+            }
+            self.depth_row = None;
+            return;
+        }
+        if self.depth_row != Some(self.selected) {
+            if let Some(depth) = self.depth.take() {
+                depth.cancel();
+            }
+            self.depth_row = Some(self.selected);
+            let trade = &self.rows[self.selected].trade;
+            if let Some(contract) = &trade.contract {
+                match DepthFeed::subscribe(self.client, contract, self.config.depth_rows) {
+                    Ok(depth) => self.depth = Some(depth),
+                    Err(e) => self
+                        .dashboard
+                        .message(format!("{}: no market depth: {}", trade.symbol, e)),
+                }
+            }
+        }
+        if let Some(depth) = &mut self.depth {
+            let symbol = &self.rows[self.selected].trade.symbol;
+            for notice in depth.poll() {
+                self.dashboard.message(format!("{}: {}", symbol, notice));
+            }
+        }
+    }
+
+    /// Depth ladder for the selected row, warning when its next order
+    /// would outsize the top of the book.
+    fn depth_panel(&self) -> Vec<ui::Line> {
+        let symbol = &self.rows[self.selected].trade.symbol;
+        let Some(feed) = self
+            .depth
+            .as_ref()
+            .filter(|_| self.depth_row == Some(self.selected))
+        else {
+            return vec![ui::Line::plain(format!("Depth {}  unavailable", symbol))];
+        };
+        let warning = self
+            .intended_order(self.selected)
+            .and_then(|(action, shares)| {
+                depth::check_liquidity(&feed.book, action, shares, self.config.depth_warn_levels)
+            });
+        depth::depth_lines(symbol, &feed.book, warning.as_ref())
+    }
+
+    /// Chart of the selected row's bars from the first interval, including
+    /// the one still being built.
+    fn chart_panel(&self) -> Vec<ui::Line> {
+        let row = &self.rows[self.selected];
+        let Some(builder) = row.builders.first() else {
+            return vec![ui::Line::plain("Chart needs BAR_INTERVALS")];
+        };
+        let mut bars: Vec<Bar> = row.history.iter().copied().collect();
+        bars.extend(builder.current().copied());
+        chart::chart_lines(
+            &row.trade,
+            builder.interval(),
+            &bars,
+            chart::WINDOWS[self.chart_zoom],
+        )
+    }
+
+    /// Shows IB's PnL next to ours and warns once about each figure that
+    /// disagrees beyond the tolerance. Nothing is compared while an order
+    /// works, as IB sees fills before we do.
+    fn check_pnl(&mut self) {
+        let trades = self.trades();
+        let commissions: HashMap<i64, Commissions> = trades
+            .iter()
+            .filter_map(|trade| {
+                let fills = self.fills.get(&trade.journal_id?)?;
+                Some((trade.journal_id?, Commissions::of(trade, fills)))
+            })
+            .collect();
+        self.dashboard.set_pnl(ui::pnl_lines(
+            self.pnl.account_pnl.as_ref(),
+            &PnlBreakdown::local(&trades, &commissions),
+        ));
+        if self.rows.iter().any(|row| row.working.is_some()) {
+            return;
+        }
+        let found = pnl::discrepancies(
+            self.pnl.account_pnl.as_ref(),
+            &self.pnl.position_pnl,
+            &trades,
+            &commissions,
+            self.config.pnl_tolerance,
+        );
+        for discrepancy in &found {
+            if self.pnl_warnings.insert(discrepancy.key()) {
+                tracing::warn!(
+                    "PnL disagrees with IB, possibly a missed fill: {}",
+                    discrepancy
+                );
+                self.dashboard.message(format!("Warning: {}", discrepancy));
+            }
+        }
+        self.pnl_warnings
+            .retain(|key| found.iter().any(|discrepancy| discrepancy.key() == *key));
+    }
+
+    /// Whether quitting now would leave a position or a working order behind.
+    fn has_exposure(&self) -> bool {
+        self.rows.iter().any(|row| {
+            row.trade.stage == Stage::Hold
+                || row.working.as_ref().is_some_and(|working| !working.filled)
+        })
+    }
+
+    fn request_shutdown(&mut self) {
+        if self.shutdown != Shutdown::Running {
+            return;
+        }
+        self.sizing_input = None;
+        if !self.has_exposure() {
+            self.shutdown = Shutdown::Exit;
+            return;
+        }
+        self.shutdown = Shutdown::Confirming;
+        self.dashboard.set_prompt(Some(
+            ">> Positions or orders open: (f)latten, (l)eave open, (c)ancel shutdown ?".to_string(),
+        ));
+    }
+
+    fn on_shutdown_key(&mut self, code: KeyCode) {
+        match (self.shutdown, code) {
+            (Shutdown::Confirming, KeyCode::Char('f')) => self.flatten_all(),
+            (_, KeyCode::Char('l')) => self.shutdown = Shutdown::Exit,
+            (Shutdown::Confirming, KeyCode::Char('c') | KeyCode::Esc) => {
+                self.shutdown = Shutdown::Running;
+                self.dashboard.set_prompt(None);
+                self.dashboard.message("Shutdown cancelled");
+            }
+            _ => {}
+        }
+    }
+
+    /// Cancels working entries and sells every held position.
+    fn flatten_all(&mut self) {
+        for index in 0..self.rows.len() {
+            let result = match &self.rows[index].working {
+                Some(working) if working.action == Action::Buy && !working.filled => self
+                    .client
+                    .cancel_order(working.order_id, "")
+                    .map(|_| ())
+                    .map_err(Error::from),
+                Some(_) => Ok(()),
+                None if self.rows[index].trade.stage == Stage::Hold => self.sell(index),
+                None => Ok(()),
+            };
+            if let Err(e) = result {
+                self.dashboard.message(format!("Error: {}", e));
+            }
+        }
+        self.shutdown = Shutdown::Flattening;
+        self.dashboard.set_prompt(Some(
+            ">> Flattening, press l to stop waiting and leave the rest open".to_string(),
+        ));
+    }
+
+    /// Stops the quote streams and journals every live trade as
+    /// disconnected, so the next start recovers whatever was left open.
+    fn disconnect(&mut self) -> Result<()> {
+        self.account.cancel();
+        self.pnl.cancel();
+        if let Some(depth) = &self.depth {
+            depth.cancel();
+        }
+        if let Some(order_updates) = &self.order_updates {
+            order_updates.cancel();
+        }
+        let mut left_open = Vec::new();
+        for row in &mut self.rows {
+            row.market_data.cancel();
+            if let Some(realtime_bars) = &row.realtime_bars {
+                realtime_bars.cancel();
+            }
+            let trade = &mut row.trade;
+            if trade.stage == Stage::Close {
+                continue;
+            }
+            if trade.position != 0 || row.working.is_some() {
+                left_open.push(json!({
+                    "symbol": trade.symbol,
+                    "position": trade.position,
+                    "working_order": row.working.as_ref().map(|working| working.order_id),
+                }));
+            }
+            trade.stage = Stage::Disconnect;
+            if trade.journal_id.is_some() {
+                self.journal.update_excursion(trade)?;
+                self.journal.update_stage(trade)?;
+            }
+        }
+        self.audit
+            .record("shutdown", json!({ "left_open": left_open }))
+    }
+
+    /// Publishes IB's real-time bars and the built bars whose interval has
+    /// ended.
+    fn poll_bars(&mut self, index: usize) {
+        let row = &mut self.rows[index];
+        let mut completed = Vec::new();
+        if let Some(realtime_bars) = &row.realtime_bars {
+            while let Some(bar) = realtime_bars.try_next() {
+                completed.push((BarSource::Realtime, (&bar).into()));
+            }
+        }
+        let now = chrono::Utc::now();
+        for builder in &mut row.builders {
+            if let Some(bar) = builder.close_due(now) {
+                completed.push((BarSource::Ticks(builder.interval()), bar));
+            }
+        }
+        for (source, bar) in completed {
+            self.on_bar(index, source, bar);
+        }
+    }
+
+    /// Feeds the ATR with bars of the first of `bar_intervals` and
+    /// publishes the bar.
+    fn on_bar(&mut self, index: usize, source: BarSource, bar: Bar) {
+        let row = &mut self.rows[index];
+        if row
+            .builders
+            .first()
+            .is_some_and(|builder| source == BarSource::Ticks(builder.interval()))
+        {
+            row.atr.on_bar(&bar);
+            row.history.push_back(bar);
+            if row.history.len() > chart::HISTORY {
+                row.history.pop_front();
+            }
+        }
+        self.bars.publish(BarEvent {
+            symbol: row.trade.symbol.clone(),
+            source,
+            bar,
+        });
+    }
+
+    fn on_tick(&mut self, index: usize, tick: TickTypes) -> Result<()> {
+        let trade = &mut self.rows[index].trade;
+        let (tick_type, price, size) = match tick {
+            TickTypes::Price(tick) => (tick.tick_type, tick.price, 0.0),
+            TickTypes::PriceSize(tick) => (tick.price_tick_type, tick.price, tick.size),
+            TickTypes::Notice(notice) => {
+                self.dashboard
+                    .message(format!("{}: {}", trade.symbol, notice.message));
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        let use_price = match tick_type {
+            TickType::Last | TickType::DelayedLast => true,
+            // Until the first trade prints, show the previous close
+            TickType::Close | TickType::DelayedClose => trade.current_price == 0.0,
+            _ => false,
+        };
+        if !use_price || price <= 0.0 {
+            return Ok(());
+        }
+
+        let samples = trade.excursion.path.len();
+        trade.update_price(price);
+        if trade.excursion.path.len() != samples && trade.journal_id.is_some() {
+            self.journal.update_excursion(trade)?;
+        }
+        if matches!(tick_type, TickType::Last | TickType::DelayedLast) {
+            self.on_trade(index, price, size);
+        }
+        Ok(())
+    }
+
+    /// Feeds a trade print to the row's VWAP and bar builders. IB's quote
+    /// stream samples trades, so volumes are approximate. The VWAP starts
+    /// over with each regular session of the trade's schedule.
+    fn on_trade(&mut self, index: usize, price: f64, size: f64) {
+        let row = &mut self.rows[index];
+        let now = chrono::Utc::now();
+        let session = row
+            .trade
+            .schedule
+            .as_ref()
+            .and_then(|schedule| schedule.regular_session_start(now));
+        if session.is_some() && session != row.vwap_session {
+            row.vwap.reset();
+            row.vwap_session = session;
+        }
+        row.vwap.update(price, size);
+        let completed: Vec<(BarSource, Bar)> = row
+            .builders
+            .iter_mut()
+            .filter_map(|builder| {
+                let bar = builder.on_trade(now, price, size)?;
+                Some((BarSource::Ticks(builder.interval()), bar))
+            })
+            .collect();
+        for (source, bar) in completed {
+            self.on_bar(index, source, bar);
+        }
+    }
+
+    /// Checks the daily loss limit once per loop, after the ticks and PnL
+    /// updates. Nothing is re-evaluated while the exits of an earlier trip
+    /// are still working.
+    fn enforce_loss_limit(&mut self) -> Result<()> {
+        self.loss_exits.retain(|order_id| {
+            self.rows.iter().any(|row| {
+                row.working
+                    .as_ref()
+                    .is_some_and(|working| working.order_id == *order_id)
+            })
+        });
+        let today = chrono::Local::now().date_naive();
+        if !self.loss_exits.is_empty() || self.orders.kill_switch().is_locked(today) {
+            return Ok(());
+        }
+        let trades = self.trades();
+        let working: Vec<i32> = self
+            .rows
+            .iter()
+            .flat_map(|row| {
+                let order = row
+                    .working
+                    .as_ref()
+                    .filter(|working| !working.filled)
+                    .map(|working| working.order_id);
+                order.into_iter().chain(row.trade.stop_order_id)
+            })
+            .collect();
+        let exits = self
+            .orders
+            .enforce_daily_loss(&trades, &working, &mut self.audit)?;
+        if !self.orders.kill_switch().is_locked(today) {
+            return Ok(());
+        }
+
+        self.dashboard
+            .message("Daily loss limit reached: orders cancelled, entries locked");
+        for (index, order_id, subscription) in exits {
+            if index >= self.rows.len() {
+                continue;
+            }
+            // The cancelled order is still followed until IB confirms it,
+            // and the exit takes its place
+            let row = &mut self.rows[index];
+            row.trade.stop_order_id = None;
+            if let Some(cancelled) = row.working.take() {
+                row.cancelled.push(cancelled);
+            }
+            self.track(index, order_id, Action::Sell, subscription);
+            self.loss_exits.push(order_id);
+        }
+        Ok(())
+    }
+
+    fn on_key(&mut self, key: KeyEvent) {
+        if self.shutdown != Shutdown::Running {
+            return self.on_shutdown_key(key.code);
+        }
+        // Raw mode turns Ctrl+C into a key press instead of a SIGINT
+        let ctrl_c =
+            key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+        if self.sizing_input.is_some() && !ctrl_c {
+            return self.on_sizing_key(key.code);
+        }
+        let index = self.selected;
+        let result = match key.code {
+            _ if ctrl_c => {
+                self.request_shutdown();
+                Ok(())
+            }
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.request_shutdown();
+                Ok(())
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+                Ok(())
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.rows.len() - 1);
+                Ok(())
+            }
+            KeyCode::Char('a') => {
+                self.show_account = !self.show_account;
+                Ok(())
+            }
+            KeyCode::Char('z') => {
+                self.sizing_input = Some(String::new());
+                self.show_sizing_prompt();
+                Ok(())
+            }
+            KeyCode::Char('d') => {
+                self.show_depth = !self.show_depth;
+                Ok(())
+            }
+            KeyCode::Char('c') => {
+                self.show_chart = !self.show_chart;
+                Ok(())
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                self.chart_zoom = self.chart_zoom.saturating_sub(1);
+                Ok(())
+            }
+            KeyCode::Char('-') => {
+                self.chart_zoom = (self.chart_zoom + 1).min(chart::WINDOWS.len() - 1);
+                Ok(())
+            }
+            _ if self.rows[index].working.is_some() => Ok(()),
+            KeyCode::Char('y') | KeyCode::Char('Y') => match self.rows[index].trade.stage {
+                Stage::Hold => self.sell(index),
+                Stage::Connect | Stage::Close => self.buy(index),
+                Stage::Open | Stage::Disconnect => Ok(()),
+            },
+            KeyCode::Char('b') => match self.rows[index].trade.stage {
+                Stage::Connect | Stage::Close => self.buy(index),
+                _ => Ok(()),
+            },
+            KeyCode::Char('s') => match self.rows[index].trade.stage {
+                Stage::Hold => self.sell(index),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            self.dashboard.message(format!("Error: {}", e));
+        }
+    }
+
+    /// Shares an entry on the row would buy, and the ATR stop they were
+    /// sized against when one is configured.
+    fn entry_size(&self, index: usize, net_liquidation: Option<f64>) -> Result<(i32, Option<f64>)> {
+        let trade = &self.rows[index].trade;
+        let atr_stop = match (self.config.atr_stop_multiple, self.rows[index].atr.value()) {
+            (Some(multiple), Some(atr)) => Some(trade.atr_stop(atr, multiple)),
+            _ => None,
+        };
+        let mode = trade.sizing.as_ref().unwrap_or(&self.config.sizing_mode);
+        let shares = match atr_stop {
+            Some(stop) => mode.shares(trade.current_price, Some(stop), net_liquidation)?,
+            None => trade.shares_to_buy(&self.config.sizing_mode, net_liquidation)?,
+        };
+        Ok((shares, atr_stop))
+    }
+
+    /// The market order the row's next answer would send, sized without
+    /// asking IB for anything.
+    fn intended_order(&self, index: usize) -> Option<(Action, i32)> {
+        let trade = &self.rows[index].trade;
+        match trade.stage {
+            Stage::Hold if trade.position > 0 => Some((Action::Sell, trade.position)),
+            Stage::Hold => Some((Action::Buy, -trade.position)),
+            Stage::Connect | Stage::Close => {
+                let net_liquidation = self.account.account.summary.net_liquidation;
+                let (shares, _) = self.entry_size(index, net_liquidation).ok()?;
+                Some((Action::Buy, shares))
+            }
+            Stage::Open | Stage::Disconnect => None,
+        }
+    }
+
+    /// Warns, without blocking, when a market order would take more than
+    /// the visible depth near the touch. Only possible while the depth
+    /// panel follows the row.
+    fn check_liquidity(&mut self, index: usize, action: Action, shares: i32) {
+        let Some(feed) = self
+            .depth
+            .as_ref()
+            .filter(|_| self.depth_row == Some(index))
+        else {
+            return;
+        };
+        if let Some(warning) =
+            depth::check_liquidity(&feed.book, action, shares, self.config.depth_warn_levels)
+        {
+            let symbol = &self.rows[index].trade.symbol;
+            tracing::warn!("{}: {}", symbol, warning);
+            self.dashboard.message(format!("{}: {}", symbol, warning));
+        }
+    }
+
+    /// Edits the sizing prompt. Enter sets the selected trade's sizing
+    /// override, Esc leaves it as it was.
+    fn on_sizing_key(&mut self, code: KeyCode) {
+        let Some(input) = &mut self.sizing_input else {
+            return;
+        };
+        match code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.sizing_input = None,
+            KeyCode::Enter => {
+                let input = self.sizing_input.take().unwrap_or_default();
+                let trade = &mut self.rows[self.selected].trade;
+                let previous = trade.sizing.clone();
+                let result = trade.set_sizing(&input).and_then(|()| match &trade.sizing {
+                    Some(mode) => mode.check_stop_source(self.config.atr_stop_multiple),
+                    None => Ok(()),
+                });
+                if result.is_err() {
+                    trade.sizing = previous;
+                }
+                let message = match result {
+                    Ok(()) => match &trade.sizing {
+                        Some(mode) => format!("{}: sizing {} for this trade", trade.symbol, mode),
+                        None => format!(
+                            "{}: sizing back to {}",
+                            trade.symbol, self.config.sizing_mode
+                        ),
+                    },
+                    Err(e) => format!("Error: {}", e),
+                };
+                self.dashboard.message(message);
+            }
+            _ => {}
+        }
+        self.show_sizing_prompt();
+    }
+
+    fn show_sizing_prompt(&mut self) {
+        let prompt = self.sizing_input.as_ref().map(|input| {
+            format!(
+                ">> Size {} (100, $5000, 2%, risk:$200; empty for {}): {}",
+                self.rows[self.selected].trade.symbol, self.config.sizing_mode, input
+            )
+        });
+        self.dashboard.set_prompt(prompt);
+    }
+
+    fn buy(&mut self, index: usize) -> Result<()> {
+        let trade = &self.rows[index].trade;
+        let atr_target = match (
+            self.config.atr_target_multiple,
+            self.rows[index].atr.value(),
+        ) {
+            (Some(multiple), Some(atr)) => Some(trade.atr_target(atr, multiple)),
+            _ => None,
+        };
+        let mode = trade.sizing.as_ref().unwrap_or(&self.config.sizing_mode);
+        let net_liquidation = match mode {
+            SizingMode::PercentOfNetLiq(_) => match self.account.account.summary.net_liquidation {
+                Some(net_liquidation) => Some(net_liquidation),
+                None => Some(sizing::net_liquidation(
+                    self.client,
+                    &self.account.account.summary.account,
+                )?),
+            },
+            _ => None,
+        };
+        let (shares, atr_stop) = self.entry_size(index, net_liquidation)?;
+        self.check_liquidity(index, Action::Buy, shares);
+        let trade = &self.rows[index].trade;
+        let (order_id, _, subscription) = self.orders.submit(trade, Action::Buy, shares, None)?;
+
+        // A new round trip gets a fresh trade; the finished one is kept
+        let trade = &mut self.rows[index].trade;
+        if trade.stage == Stage::Close {
+            let mut next = Trade::new(trade.symbol.clone());
+            next.contract_id = trade.contract_id;
+            next.account = trade.account.clone();
+            next.contract = trade.contract.clone();
+            next.schedule = trade.schedule.clone();
+            next.strategy = trade.strategy.clone();
+            next.sizing = trade.sizing.clone();
+            next.current_price = trade.current_price;
+            next.excursion = Excursion::new(trade.excursion.sample_interval_secs);
+            self.closed.push(std::mem::replace(trade, next));
+        }
+
+        // Journal the entry while it works so a crash leaves a trace
+        let trade = &mut self.rows[index].trade;
+        if let Some(stop) = atr_stop {
+            trade.stop_price = Some(stop);
+            self.dashboard
+                .message(format!("{}: ATR stop ${:.2}", trade.symbol, stop));
+        }
+        if let Some(target) = atr_target {
+            trade.target_price = Some(target);
+            self.dashboard
+                .message(format!("{}: ATR target ${:.2}", trade.symbol, target));
+        }
+        trade.stage = Stage::Open;
+        self.journal.open_trade(trade)?;
+        self.dashboard.message(format!(
+            "Buying {} {} (order #{})",
+            shares, trade.symbol, order_id
+        ));
+        self.track(index, order_id, Action::Buy, subscription);
+        Ok(())
+    }
+
+    fn sell(&mut self, index: usize) -> Result<()> {
+        if let Some((action, shares)) = self.intended_order(index) {
+            self.check_liquidity(index, action, shares);
+        }
+        self.cancel_stop(index);
+        let trade = &self.rows[index].trade;
+        if let Some((order_id, subscription)) = self.orders.flatten(trade)? {
+            self.dashboard.message(format!(
+                "Selling {} {} (order #{})",
+                trade.position, trade.symbol, order_id
+            ));
+            self.track(index, order_id, Action::Sell, subscription);
+        }
+        Ok(())
+    }
+
+    fn track(
+        &mut self,
+        index: usize,
+        order_id: i32,
+        action: Action,
+        subscription: Subscription<'a, PlaceOrder>,
+    ) {
+        self.rows[index].working = Some(WorkingOrder {
+            order_id,
+            action,
+            subscription: Some(subscription),
+            recorded: false,
+            filled: false,
+            cancelled: false,
+            fills: Vec::new(),
+        });
+    }
+
+    fn poll_order(&mut self, index: usize) -> Result<()> {
+        if let Some(working) = self.rows[index].working.take() {
+            self.rows[index].working = self.poll_working(index, working)?;
+        }
+        if let Some(stop) = self.rows[index].stop.take() {
+            self.rows[index].stop = self.poll_working(index, stop)?;
+        }
+        for cancelled in std::mem::take(&mut self.rows[index].cancelled) {
+            if let Some(cancelled) = self.poll_working(index, cancelled)? {
+                self.rows[index].cancelled.push(cancelled);
+            }
+        }
+        Ok(())
+    }
+
+    /// Journals an order's updates and returns it while it still has some
+    /// to come.
+    fn poll_working(
+        &mut self,
+        index: usize,
+        mut working: WorkingOrder<'a>,
+    ) -> Result<Option<WorkingOrder<'a>>> {
+        while let Some(event) = working
+            .subscription
+            .as_ref()
+            .and_then(|subscription| subscription.try_next())
+        {
+            self.on_order_event(index, &mut working, event)?;
+        }
+
+        Ok((!working.is_done()).then_some(working))
+    }
+
+    /// Hands updates from the shared order stream to the recovered orders
+    /// they belong to.
+    fn poll_order_updates(&mut self) -> Result<()> {
+        let Some(order_updates) = &self.order_updates else {
+            return Ok(());
+        };
+        let mut events = Vec::new();
+        while let Some(update) = order_updates.try_next() {
+            events.push(match update {
+                OrderUpdate::OpenOrder(data) => PlaceOrder::OpenOrder(data),
+                OrderUpdate::OrderStatus(status) => PlaceOrder::OrderStatus(status),
+                OrderUpdate::ExecutionData(data) => PlaceOrder::ExecutionData(data),
+                OrderUpdate::CommissionReport(report) => PlaceOrder::CommissionReport(report),
+                OrderUpdate::Message(_) => continue,
+            });
+        }
+        for event in events {
+            let owns = |working: &Option<WorkingOrder>| {
+                working
+                    .as_ref()
+                    .is_some_and(|working| working.subscription.is_none() && working.owns(&event))
+            };
+            let Some((index, is_stop)) = self.rows.iter().enumerate().find_map(|(index, row)| {
+                if owns(&row.working) {
+                    Some((index, false))
+                } else if owns(&row.stop) {
+                    Some((index, true))
+                } else {
+                    None
+                }
+            }) else {
+                continue;
+            };
+            let row = &mut self.rows[index];
+            let slot = if is_stop {
+                &mut row.stop
+            } else {
+                &mut row.working
+            };
+            let Some(mut working) = slot.take() else {
+                continue;
+            };
+            self.on_order_event(index, &mut working, event)?;
+            if !working.is_done() {
+                let row = &mut self.rows[index];
+                if is_stop {
+                    row.stop = Some(working);
+                } else {
+                    row.working = Some(working);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Journals one update of an order and moves its trade along.
+    fn on_order_event(
+        &mut self,
+        index: usize,
+        working: &mut WorkingOrder<'a>,
+        event: PlaceOrder,
+    ) -> Result<()> {
+        let trade_id = self.rows[index].trade.journal_id;
+        match event {
+            PlaceOrder::OpenOrder(data) if !working.recorded => {
+                if let Some(trade_id) = trade_id {
+                    self.journal
+                        .record_order(trade_id, data.order_id, &data.order)?;
+                }
+                working.recorded = true;
+            }
+            PlaceOrder::OpenOrder(_) => {}
+            PlaceOrder::OrderStatus(status) => {
+                self.journal
+                    .update_order_status(working.order_id, &status.status)?;
+                match status.status.as_str() {
+                    "Filled" if !working.filled => {
+                        working.filled = true;
+                        let trade = &mut self.rows[index].trade;
+                        if trade.stop_order_id == Some(working.order_id) {
+                            trade.stop_order_id = None;
+                        }
+                        self.on_filled(
+                            index,
+                            working.action,
+                            status.filled as i32,
+                            status.average_fill_price,
+                        )?;
+                    }
+                    "Cancelled" | "ApiCancelled" | "Inactive" if !working.filled => {
+                        working.cancelled = true;
+                        self.on_cancelled(index, working, &status)?;
+                    }
+                    _ => {}
+                }
+            }
+            PlaceOrder::ExecutionData(data) => {
+                let fill = Fill::from_execution(&data);
+                if let Some(trade_id) = trade_id {
+                    self.journal.record_fill(trade_id, &fill)?;
+                    let fills = self.fills.entry(trade_id).or_default();
+                    // A repeated execution replaces the one recorded before
+                    fills.retain(|recorded| recorded.execution_id != fill.execution_id);
+                    fills.push(fill.clone());
+                }
+                working.fills.push(fill);
+            }
+            PlaceOrder::CommissionReport(report) => {
+                self.journal.record_commission(&report)?;
+                let recorded = trade_id.and_then(|trade_id| self.fills.get_mut(&trade_id));
+                for fill in working
+                    .fills
+                    .iter_mut()
+                    .chain(recorded.into_iter().flatten())
+                    .filter(|fill| fill.execution_id == report.execution_id)
+                {
+                    fill.commission = Some(report.commission);
+                }
+            }
+            PlaceOrder::Message(notice) => {
+                self.dashboard
+                    .message(format!("Order #{}: {}", working.order_id, notice.message));
+            }
+        }
+        Ok(())
+    }
+
+    fn on_filled(&mut self, index: usize, action: Action, shares: i32, price: f64) -> Result<()> {
+        let trade = &mut self.rows[index].trade;
+        if action == Action::Buy {
+            trade.open_position(shares, price);
+            self.journal.update_entry(trade)?;
+            self.dashboard.message(format!(
+                "Bought {} {} @ ${:.2}",
+                shares, trade.symbol, price
+            ));
+            if let Some(stop) = trade.stop_price {
+                // The position stands either way, so a refused stop is
+                // reported rather than ending the session
+                if let Err(e) = self.send_stop(index, stop) {
+                    let symbol = &self.rows[index].trade.symbol;
+                    tracing::warn!("{}: stop order not sent: {}", symbol, e);
+                    self.dashboard
+                        .message(format!("{}: stop order not sent: {}", symbol, e));
+                }
+            }
+        } else {
+            trade.update_price(price);
+            let pnl = trade.close_position();
+            self.journal.close_trade(trade)?;
+            self.dashboard.message(format!(
+                "Sold {} {} @ ${:.2}, final PnL {}",
+                shares,
+                trade.symbol,
+                price,
+                format_money(pnl)
+            ));
+            self.cancel_stop(index);
+        }
+        Ok(())
+    }
+
+    /// Protects a filled entry with a stop order for the whole position.
+    fn send_stop(&mut self, index: usize, stop_price: f64) -> Result<()> {
+        let trade = &mut self.rows[index].trade;
+        let (order_id, order, subscription) = self.orders.attach_stop(trade, stop_price)?;
+        self.journal
+            .record_order(journal::journal_id(trade)?, order_id, &order)?;
+        self.dashboard.message(format!(
+            "{}: stop at ${:.2} (order #{})",
+            trade.symbol, stop_price, order_id
+        ));
+        self.rows[index].stop = Some(WorkingOrder {
+            order_id,
+            action: order.action,
+            subscription: Some(subscription),
+            recorded: true,
+            filled: false,
+            cancelled: false,
+            fills: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Cancels the trade's stop once its position is closed another way. A
+    /// failed cancel is reported, and doesn't hold up the exit.
+    fn cancel_stop(&mut self, index: usize) {
+        let trade = &mut self.rows[index].trade;
+        if let Some(order_id) = trade.stop_order_id.take() {
+            if let Err(e) = self.client.cancel_order(order_id, "") {
+                self.dashboard.message(format!(
+                    "{}: cancelling stop #{} failed: {}",
+                    trade.symbol, order_id, e
+                ));
+            }
+        }
+    }
+
+    /// An entry cancelled after a partial fill still opened a position, so
+    /// it is held with the shares that filled.
+    fn on_cancelled(
+        &mut self,
+        index: usize,
+        working: &WorkingOrder,
+        status: &OrderStatus,
+    ) -> Result<()> {
+        self.dashboard.message(format!(
+            "Order #{} {}",
+            working.order_id,
+            status.status.to_lowercase()
+        ));
+        let trade = &mut self.rows[index].trade;
+        if trade.stop_order_id == Some(working.order_id) {
+            trade.stop_order_id = None;
+        }
+        if working.action == Action::Buy && trade.stage == Stage::Open {
+            if status.filled > 0.0 {
+                return self.on_filled(
+                    index,
+                    Action::Buy,
+                    status.filled as i32,
+                    status.average_fill_price,
+                );
+            }
+            trade.stage = Stage::Close;
+            self.journal.update_stage(trade)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use crate::error::Result;
+use serde_json::{json, Value};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Append-only JSON lines log of safety-relevant events.
+pub struct AuditLog {
+    file: File,
+}
+
+impl AuditLog {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AuditLog { file })
+    }
+
+    pub fn record(&mut self, event: &str, details: Value) -> Result<()> {
+        let entry = json!({
+            "time": chrono::Local::now().to_rfc3339(),
+            "event": event,
+            "details": details,
+        });
+        writeln!(self.file, "{}", entry)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
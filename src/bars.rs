@@ -0,0 +1,231 @@
+use crate::error::{Error, Result};
+use crate::history::Bar;
+use chrono::{DateTime, Utc};
+use ibapi::client::Subscription;
+use ibapi::contracts::Contract;
+use ibapi::market_data::realtime::{self, BarSize, WhatToShow};
+use ibapi::Client;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// How the trade stream is cut into bars.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarInterval {
+    /// A bar per clock interval of this many seconds.
+    Time(u32),
+    /// A bar per this many shares traded.
+    Volume(f64),
+    /// A new bar whenever the high-low range would exceed this many dollars.
+    Range(f64),
+}
+
+impl fmt::Display for BarInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BarInterval::Time(secs) if secs % 3600 == 0 => write!(f, "{}h", secs / 3600),
+            BarInterval::Time(secs) if secs % 60 == 0 => write!(f, "{}m", secs / 60),
+            BarInterval::Time(secs) => write!(f, "{}s", secs),
+            BarInterval::Volume(shares) => write!(f, "vol:{}", shares),
+            BarInterval::Range(range) => write!(f, "range:{}", range),
+        }
+    }
+}
+
+impl FromStr for BarInterval {
+    type Err = Error;
+
+    /// `30s`, `1m`, `5m`, `1h`, `vol:5000` or `range:0.50`.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        let invalid = || Error::Config(format!("Invalid bar interval: {}", s));
+        let interval = if let Some(shares) = s.strip_prefix("vol:") {
+            BarInterval::Volume(shares.parse().map_err(|_| invalid())?)
+        } else if let Some(range) = s.strip_prefix("range:") {
+            BarInterval::Range(range.parse().map_err(|_| invalid())?)
+        } else {
+            let unit = s.chars().last().ok_or_else(invalid)?;
+            let count: u32 = s[..s.len() - unit.len_utf8()]
+                .parse()
+                .map_err(|_| invalid())?;
+            let secs = match unit {
+                's' => Some(count),
+                'm' => count.checked_mul(60),
+                'h' => count.checked_mul(3600),
+                _ => None,
+            };
+            BarInterval::Time(secs.ok_or_else(invalid)?)
+        };
+        match interval {
+            BarInterval::Time(0) => Err(invalid()),
+            // NaN compares false against everything, so test for the valid case
+            BarInterval::Volume(size) | BarInterval::Range(size)
+                if !(size.is_finite() && size > 0.0) =>
+            {
+                Err(invalid())
+            }
+            interval => Ok(interval),
+        }
+    }
+}
+
+/// Builds bars of one interval from trades. Bars take the time of their
+/// first trade, or the start of their clock interval for time bars.
+#[derive(Debug, Clone)]
+pub struct BarBuilder {
+    interval: BarInterval,
+    current: Option<Bar>,
+}
+
+impl BarBuilder {
+    pub fn new(interval: BarInterval) -> Self {
+        BarBuilder {
+            interval,
+            current: None,
+        }
+    }
+
+    pub fn interval(&self) -> BarInterval {
+        self.interval
+    }
+
+    /// The bar still being built.
+    pub fn current(&self) -> Option<&Bar> {
+        self.current.as_ref()
+    }
+
+    /// Adds a trade and returns the bar it completed, if any. A volume bar
+    /// completes on the trade that fills it, so it may run over.
+    pub fn on_trade(&mut self, time: DateTime<Utc>, price: f64, size: f64) -> Option<Bar> {
+        let completed = match (self.interval, &self.current) {
+            (BarInterval::Time(secs), Some(bar)) if bar.time != bucket(time, secs) => {
+                self.current.take()
+            }
+            (BarInterval::Range(range), Some(bar))
+                if bar.high.max(price) - bar.low.min(price) > range =>
+            {
+                self.current.take()
+            }
+            _ => None,
+        };
+
+        match &mut self.current {
+            Some(bar) => {
+                bar.high = bar.high.max(price);
+                bar.low = bar.low.min(price);
+                bar.close = price;
+                if bar.volume + size > 0.0 {
+                    bar.wap = (bar.wap * bar.volume + price * size) / (bar.volume + size);
+                }
+                bar.volume += size;
+                bar.count += 1;
+            }
+            None => {
+                let time = match self.interval {
+                    BarInterval::Time(secs) => bucket(time, secs),
+                    _ => time,
+                };
+                self.current = Some(Bar {
+                    time,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    volume: size,
+                    wap: price,
+                    count: 1,
+                });
+            }
+        }
+
+        match (self.interval, &self.current) {
+            (BarInterval::Volume(shares), Some(bar)) if bar.volume >= shares => self.current.take(),
+            _ => completed,
+        }
+    }
+
+    /// Completes a time bar whose interval has ended by `now`, so quiet
+    /// markets still produce bars on time.
+    pub fn close_due(&mut self, now: DateTime<Utc>) -> Option<Bar> {
+        match (self.interval, &self.current) {
+            (BarInterval::Time(secs), Some(bar)) if bar.time < bucket(now, secs) => {
+                self.current.take()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Start of the `secs` long clock interval holding `time`.
+fn bucket(time: DateTime<Utc>, secs: u32) -> DateTime<Utc> {
+    let secs = i64::from(secs);
+    DateTime::from_timestamp(time.timestamp().div_euclid(secs) * secs, 0).unwrap_or(time)
+}
+
+/// Where a published bar came from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarSource {
+    /// IB's real-time 5 second bars.
+    Realtime,
+    /// Built from the quote stream by a `BarBuilder`.
+    Ticks(BarInterval),
+}
+
+/// A completed bar for one symbol.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BarEvent {
+    pub symbol: String,
+    pub source: BarSource,
+    pub bar: Bar,
+}
+
+/// Fans completed bars out to every subscriber. Subscribers that have
+/// hung up are dropped on the next publish.
+#[derive(Debug, Default)]
+pub struct BarBus {
+    subscribers: Vec<Sender<BarEvent>>,
+}
+
+impl BarBus {
+    pub fn new() -> Self {
+        BarBus::default()
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<BarEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn publish(&mut self, event: BarEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
+
+impl From<&realtime::Bar> for Bar {
+    fn from(bar: &realtime::Bar) -> Self {
+        Bar {
+            time: DateTime::from_timestamp(bar.date.unix_timestamp(), 0).unwrap_or_default(),
+            open: bar.open,
+            high: bar.high,
+            low: bar.low,
+            close: bar.close,
+            volume: bar.volume,
+            wap: bar.wap,
+            count: bar.count,
+        }
+    }
+}
+
+/// Subscribes to IB's 5 second trade bars for the contract.
+pub fn subscribe_realtime<'a>(
+    client: &'a Client,
+    contract: &Contract,
+    use_rth: bool,
+) -> Result<Subscription<'a, realtime::Bar>> {
+    Ok(client.realtime_bars(contract, BarSize::Sec5, WhatToShow::Trades, use_rth)?)
+}
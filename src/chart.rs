@@ -0,0 +1,167 @@
+use crate::bars::BarInterval;
+use crate::history::Bar;
+use crate::trade::Trade;
+use crate::ui::Line;
+use crossterm::style::Color;
+
+/// Bars shown at each zoom step, from closest to widest.
+pub const WINDOWS: [usize; 5] = [15, 30, 60, 120, 240];
+/// Completed bars kept per symbol, enough for the widest window.
+pub const HISTORY: usize = WINDOWS[WINDOWS.len() - 1];
+/// Columns of candles at most; wider windows merge bars into each column.
+pub const WIDTH: usize = 60;
+/// Rows of candles.
+pub const HEIGHT: usize = 12;
+
+/// A horizontal price line drawn across the chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    pub label: &'static str,
+    pub price: f64,
+}
+
+/// Merges runs of consecutive bars so at most `columns` remain. Runs are
+/// counted back from the latest bar, so only the oldest may be short.
+pub fn merge(bars: &[Bar], columns: usize) -> Vec<Bar> {
+    if bars.is_empty() || columns == 0 {
+        return Vec::new();
+    }
+    let per_column = bars.len().div_ceil(columns);
+    let mut merged: Vec<Bar> = bars.rchunks(per_column).map(combine).collect();
+    merged.reverse();
+    merged
+}
+
+fn combine(bars: &[Bar]) -> Bar {
+    let first = bars[0];
+    let last = bars[bars.len() - 1];
+    let volume: f64 = bars.iter().map(|bar| bar.volume).sum();
+    let wap = if volume > 0.0 {
+        bars.iter().map(|bar| bar.wap * bar.volume).sum::<f64>() / volume
+    } else {
+        last.close
+    };
+    Bar {
+        time: first.time,
+        open: first.open,
+        high: bars.iter().map(|bar| bar.high).fold(f64::MIN, f64::max),
+        low: bars.iter().map(|bar| bar.low).fold(f64::MAX, f64::min),
+        close: last.close,
+        volume,
+        wap,
+        count: bars.iter().map(|bar| bar.count).sum(),
+    }
+}
+
+/// Draws one candle per bar, `height` rows tall, with the price axis on the
+/// left and each level as a labelled line. Rising bars get solid bodies and
+/// falling bars shaded ones; candles are drawn over level lines.
+pub fn render(bars: &[Bar], levels: &[Level], height: usize) -> Vec<Line> {
+    let height = height.max(2);
+    let (mut low, mut high) = bars
+        .iter()
+        .flat_map(|bar| [bar.low, bar.high])
+        .chain(levels.iter().map(|level| level.price))
+        .fold((f64::MAX, f64::MIN), |(low, high), price| {
+            (low.min(price), high.max(price))
+        });
+    if low > high {
+        return Vec::new();
+    }
+    // A flat market still needs a range to scale against
+    if high - low < 0.01 {
+        high += 0.005;
+        low -= 0.005;
+    }
+    let step = (high - low) / (height - 1) as f64;
+    let row_of = |price: f64| ((high - price) / step).round() as usize;
+
+    (0..height)
+        .map(|row| {
+            let axis = match row {
+                0 => format!("{:>10.2} ┤", high),
+                row if row == height - 1 => format!("{:>10.2} ┤", low),
+                _ => format!("{:>10} │", ""),
+            };
+            let row_levels: Vec<&Level> = levels
+                .iter()
+                .filter(|level| row_of(level.price) == row)
+                .collect();
+            let candles: String = bars
+                .iter()
+                .map(|bar| {
+                    let body = row_of(bar.open.max(bar.close))..=row_of(bar.open.min(bar.close));
+                    let wick = row_of(bar.high)..=row_of(bar.low);
+                    if body.contains(&row) {
+                        if bar.close >= bar.open {
+                            '█'
+                        } else {
+                            '▒'
+                        }
+                    } else if wick.contains(&row) {
+                        '│'
+                    } else if !row_levels.is_empty() {
+                        '─'
+                    } else {
+                        ' '
+                    }
+                })
+                .collect();
+            let labels: String = row_levels
+                .iter()
+                .map(|level| format!(" {} {:.2}", level.label, level.price))
+                .collect();
+            let text = format!("{}{}{}", axis, candles, labels);
+            let labelled = |label| row_levels.iter().any(|level| level.label == label);
+            if labelled("stop") {
+                Line::colored(text, Color::Red)
+            } else if labelled("target") {
+                Line::colored(text, Color::Green)
+            } else {
+                Line::plain(text)
+            }
+        })
+        .collect()
+}
+
+/// The chart panel for a trade: the last `window` bars, newest on the
+/// right, with the last price and any entry, stop and target overlaid.
+pub fn chart_lines(trade: &Trade, interval: BarInterval, bars: &[Bar], window: usize) -> Vec<Line> {
+    let mut lines = vec![Line::plain(format!(
+        "Chart {}  {} x {}  (+/- zoom)",
+        trade.symbol, interval, window
+    ))];
+    let bars = merge(&bars[bars.len().saturating_sub(window)..], WIDTH);
+    if bars.is_empty() {
+        lines.push(Line::plain("  No bars yet"));
+        return lines;
+    }
+
+    let mut levels = Vec::new();
+    if trade.current_price > 0.0 {
+        levels.push(Level {
+            label: "last",
+            price: trade.current_price,
+        });
+    }
+    if trade.position != 0 {
+        levels.push(Level {
+            label: "entry",
+            price: trade.entry_price,
+        });
+    }
+    if let Some(stop) = trade.stop_price {
+        levels.push(Level {
+            label: "stop",
+            price: stop,
+        });
+    }
+    if let Some(target) = trade.target_price {
+        levels.push(Level {
+            label: "target",
+            price: target,
+        });
+    }
+    lines.extend(render(&bars, &levels, HEIGHT));
+    lines
+}
@@ -0,0 +1,289 @@
+use crate::config::ConfigOverrides;
+use crate::export::{ExportColumn, ExportFormat};
+use crate::history::{self, BarType};
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use clap_complete::Shell;
+use ibapi::market_data::historical::BarSize;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Terminal trading app for Interactive Brokers TWS",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    /// Arguments for `trade`, which runs when no subcommand is given
+    #[command(flatten)]
+    pub trade: TradeArgs,
+
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Flags accepted by every subcommand.
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    /// Print trade state and results as JSON instead of text
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// TWS host, overrides TWS_HOST
+    #[arg(long, global = true)]
+    pub host: Option<String>,
+
+    /// TWS port, overrides TWS_PORT
+    #[arg(long, global = true)]
+    pub port: Option<u16>,
+
+    /// API client id, overrides CLIENT_ID
+    #[arg(long, global = true)]
+    pub client_id: Option<i32>,
+
+    /// Account to trade and report on, overrides ACCOUNT
+    #[arg(long, global = true)]
+    pub account: Option<String>,
+
+    /// Load settings from .env.<PROFILE> ahead of .env
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Trade the paper account, overrides PAPER_TRADING
+    #[arg(long, global = true, conflicts_with = "live")]
+    pub paper: bool,
+
+    /// Trade the live account, overrides PAPER_TRADING
+    #[arg(long, global = true)]
+    pub live: bool,
+}
+
+impl Cli {
+    pub fn json(&self) -> bool {
+        self.global.json
+    }
+
+    /// Settings from the global flags that take precedence over the
+    /// environment.
+    pub fn overrides(&self) -> ConfigOverrides {
+        let global = &self.global;
+        ConfigOverrides {
+            profile: global.profile.clone(),
+            tws_host: global.host.clone(),
+            tws_port: global.port,
+            client_id: global.client_id,
+            account: global.account.clone(),
+            paper_trading: match (global.paper, global.live) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Watch symbols and trade them from the dashboard (the default)
+    Trade(TradeArgs),
+    /// Buy without prompts, checked against the policy file
+    Buy(OrderArgs),
+    /// Sell an open trade without prompts, checked against the policy file
+    Sell(OrderArgs),
+    /// Snapshot quotes for one or more symbols
+    Quote(QuoteArgs),
+    /// Historical bars for a symbol as CSV, cached locally
+    Bars(BarsArgs),
+    /// Accounts managed by this login
+    Accounts,
+    /// Account balances and portfolio, reconciled against journaled trades
+    Account,
+    /// Positions held at IB, across all accounts unless one is selected
+    Positions,
+    /// Orders working at IB, across all accounts unless one is selected
+    Orders,
+    /// Trades recorded in the journal
+    History(HistoryArgs),
+    /// Performance statistics over completed trades in the journal
+    Report(ReportArgs),
+    /// Export completed trades from the journal to CSV
+    Export(ExportArgs),
+    /// Print the effective configuration
+    Config,
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct TradeArgs {
+    /// Ticker symbols to watch and trade, added to WATCHLIST; prompted for
+    /// when neither is given
+    pub symbols: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct QuoteArgs {
+    /// Ticker symbols to quote
+    #[arg(required = true)]
+    pub symbols: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct BarsArgs {
+    /// Ticker symbol
+    pub symbol: String,
+
+    /// 1s, 5s, 15s, 30s, 1m, 2m, 3m, 5m, 15m, 20m, 30m, 1h, 2h, 3h, 4h,
+    /// 8h or 1d
+    #[arg(long, default_value = "1d", value_parser = parse_bar_size)]
+    pub size: BarSize,
+
+    /// `trades`, `midpoint` or `bid_ask`
+    #[arg(long = "type", default_value = "trades", value_parser = parse_bar_type)]
+    pub bar_type: BarType,
+
+    /// Include bars outside regular trading hours
+    #[arg(long)]
+    pub outside_rth: bool,
+
+    /// First date to include (YYYY-MM-DD), --days before --to when omitted
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+
+    /// Last date to include (YYYY-MM-DD), today when omitted
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+
+    /// Days to load when --from is omitted
+    #[arg(long, default_value_t = 30, conflicts_with = "from")]
+    pub days: u32,
+
+    /// File to write, stdout when omitted
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct OrderArgs {
+    /// Ticker symbol to trade
+    pub symbol: String,
+
+    /// Shares to trade; buys default to the sizing mode, sells to the whole
+    /// position
+    #[arg(long)]
+    pub qty: Option<i32>,
+
+    /// Limit price, a market order when omitted
+    #[arg(long)]
+    pub limit: Option<f64>,
+
+    /// Wait for the order to fill, giving up after this many seconds
+    #[arg(long, value_name = "SECS", num_args = 0..=1, default_missing_value = "60")]
+    pub wait_fill: Option<u64>,
+
+    /// Confirm the order; needed unless the policy file sets auto_confirm
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// File to write, stdout when omitted
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// `csv` for one row per trade, `tax-lots` for Form 8949 style rows
+    /// matched to lots with `LOT_METHOD`
+    #[arg(long, default_value = "csv", value_parser = parse_format)]
+    pub format: ExportFormat,
+
+    /// Comma separated columns for the csv format, all columns when omitted
+    #[arg(long, value_delimiter = ',', value_parser = parse_column)]
+    pub columns: Vec<ExportColumn>,
+
+    /// First close date to include (YYYY-MM-DD)
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+
+    /// Last close date to include (YYYY-MM-DD)
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+
+    /// Only export trades in this symbol
+    #[arg(long)]
+    pub symbol: Option<String>,
+
+    /// Only export trades with this strategy tag
+    #[arg(long)]
+    pub strategy: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// Only include trades closed today
+    #[arg(long, conflicts_with_all = ["from", "to"])]
+    pub session: bool,
+
+    /// First close date to include (YYYY-MM-DD)
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+
+    /// Last close date to include (YYYY-MM-DD)
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+
+    /// Only include trades in this symbol
+    #[arg(long)]
+    pub symbol: Option<String>,
+
+    /// Only include trades with this strategy tag
+    #[arg(long)]
+    pub strategy: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct HistoryArgs {
+    /// First open date to include (YYYY-MM-DD)
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+
+    /// Last open date to include (YYYY-MM-DD)
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+
+    /// Only list trades in this symbol
+    #[arg(long)]
+    pub symbol: Option<String>,
+
+    /// Only list trades with this strategy tag
+    #[arg(long)]
+    pub strategy: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    /// Shell to generate completions for
+    pub shell: Shell,
+}
+
+fn parse_format(value: &str) -> Result<ExportFormat, String> {
+    value.parse().map_err(|e: crate::Error| e.to_string())
+}
+
+fn parse_bar_size(value: &str) -> Result<BarSize, String> {
+    history::parse_bar_size(value).map_err(|e| e.to_string())
+}
+
+fn parse_bar_type(value: &str) -> Result<BarType, String> {
+    value.parse().map_err(|e: crate::Error| e.to_string())
+}
+
+fn parse_column(value: &str) -> Result<ExportColumn, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|e: crate::Error| e.to_string())
+}
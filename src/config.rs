@@ -1,17 +1,115 @@
+use crate::account::FaMethod;
+use crate::bars::BarInterval;
 use crate::error::{Error, Result};
+use crate::excursion::DEFAULT_SAMPLE_INTERVAL_SECS;
+use crate::lots::LotMethod;
+use crate::risk::RiskLimits;
+use crate::session::OutsideRthPolicy;
+use crate::sizing::SizingMode;
 use dotenv::dotenv;
+use serde::{Deserialize, Serialize};
 use std::env;
+use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+/// Settings given on the command line, applied over the environment.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    /// Reads `.env.<profile>` before `.env`, so its values win.
+    pub profile: Option<String>,
+    pub tws_host: Option<String>,
+    pub tws_port: Option<u16>,
+    pub client_id: Option<i32>,
+    pub paper_trading: Option<bool>,
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub tws_host: String,
     pub tws_port: u16,
     pub client_id: i32,
     pub paper_trading: bool,
     pub log_level: String,
+    pub sizing_mode: SizingMode,
+    pub risk_limits: RiskLimits,
+    pub daily_loss_limit: Option<f64>,
+    pub flatten_on_loss_limit: bool,
+    pub audit_log_path: String,
+    /// File the kill switch keeps its trip date in.
+    pub kill_switch_state_path: String,
+    pub journal_path: String,
+    /// SQLite file historical bars are cached in.
+    pub bar_cache_path: String,
+    pub watchlist: Vec<String>,
+    /// How the tax-lot export and report match sales to lots.
+    pub lot_method: LotMethod,
+    pub price_sample_secs: u64,
+    pub outside_rth: bool,
+    pub outside_rth_policy: OutsideRthPolicy,
+    pub policy_path: String,
+    /// Largest difference between IB's and our unrealized PnL that isn't
+    /// flagged, in dollars.
+    pub pnl_tolerance: f64,
+    /// Account to trade and report on; the first managed account when unset.
+    pub account: Option<String>,
+    /// FA allocation group for orders. When set, orders go to the group
+    /// instead of `account`.
+    pub fa_group: Option<String>,
+    /// Allocation method for `fa_group`; TWS uses the group's own method
+    /// when unset.
+    pub fa_method: Option<FaMethod>,
+    /// Bars built from each symbol's quote stream while trading.
+    pub bar_intervals: Vec<BarInterval>,
+    /// Also subscribe to IB's 5 second real-time bars.
+    pub realtime_bars: bool,
+    /// Bars in the ATR, taken from the first of `bar_intervals`.
+    pub atr_period: usize,
+    /// When set, entries get a stop this many ATRs below the price, which
+    /// risk-based sizing uses and which is sent as a stop order once the
+    /// entry fills.
+    pub atr_stop_multiple: Option<f64>,
+    /// When set, entries get a profit target this many ATRs above the
+    /// price, drawn on the chart.
+    pub atr_target_multiple: Option<f64>,
+    /// Rows of market depth per side for the selected symbol, subscribed
+    /// while the depth panel is shown; 0 turns depth off.
+    pub depth_rows: i32,
+    /// Top depth levels a market order is checked against.
+    pub depth_warn_levels: usize,
 }
 
 impl Config {
+    /// Loads the profile's env file if one is named, then the environment,
+    /// then applies the overrides.
+    pub fn load(overrides: &ConfigOverrides) -> Result<Self> {
+        if let Some(profile) = &overrides.profile {
+            let file = format!(".env.{}", profile);
+            dotenv::from_filename(&file)
+                .map_err(|e| Error::Config(format!("Cannot load profile {}: {}", file, e)))?;
+        }
+        let mut config = Config::from_env()?;
+        config.apply(overrides);
+        Ok(config)
+    }
+
+    pub fn apply(&mut self, overrides: &ConfigOverrides) {
+        if let Some(host) = &overrides.tws_host {
+            self.tws_host = host.clone();
+        }
+        if let Some(port) = overrides.tws_port {
+            self.tws_port = port;
+        }
+        if let Some(client_id) = overrides.client_id {
+            self.client_id = client_id;
+        }
+        if let Some(paper_trading) = overrides.paper_trading {
+            self.paper_trading = paper_trading;
+        }
+        if let Some(account) = &overrides.account {
+            self.account = Some(account.clone());
+        }
+    }
+
     pub fn from_env() -> Result<Self> {
         dotenv().ok();
         
@@ -29,6 +127,85 @@ impl Config {
             .parse::<bool>()
             .unwrap_or(true);
         let log_level = env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
+        let sizing_mode = env::var("SIZING_MODE")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse::<SizingMode>()
+            .map_err(|e| Error::Config(format!("Invalid SIZING_MODE: {}", e)))?;
+        let defaults = RiskLimits::default();
+        let risk_limits = RiskLimits {
+            max_shares_per_order: parse_var("MAX_SHARES_PER_ORDER", defaults.max_shares_per_order)?,
+            max_notional_per_order: parse_var("MAX_NOTIONAL_PER_ORDER", defaults.max_notional_per_order)?,
+            max_position_per_symbol: parse_var("MAX_POSITION_PER_SYMBOL", defaults.max_position_per_symbol)?,
+            max_price_deviation_pct: parse_var("MAX_PRICE_DEVIATION_PCT", defaults.max_price_deviation_pct)?,
+            max_orders_per_minute: parse_var("MAX_ORDERS_PER_MINUTE", defaults.max_orders_per_minute)?,
+        };
+        let daily_loss_limit = match env::var("DAILY_LOSS_LIMIT") {
+            Ok(value) => Some(
+                value
+                    .parse::<f64>()
+                    .map_err(|e| Error::Config(format!("Invalid DAILY_LOSS_LIMIT: {}", e)))?,
+            ),
+            Err(_) => None,
+        };
+        let flatten_on_loss_limit = parse_var("FLATTEN_ON_LOSS_LIMIT", false)?;
+        let audit_log_path = env::var("AUDIT_LOG_PATH").unwrap_or_else(|_| "logs/audit.log".to_string());
+        let kill_switch_state_path = env::var("KILL_SWITCH_STATE_PATH").unwrap_or_else(|_| "data/kill_switch.state".to_string());
+        let journal_path = env::var("JOURNAL_PATH").unwrap_or_else(|_| "data/journal.db".to_string());
+        let bar_cache_path =
+            env::var("BAR_CACHE_PATH").unwrap_or_else(|_| "data/bars.db".to_string());
+        let watchlist = env::var("WATCHLIST")
+            .map(|symbols| parse_symbols(&symbols))
+            .unwrap_or_default();
+        let lot_method = parse_var("LOT_METHOD", LotMethod::Fifo)?;
+        lot_method.check_replayable().map_err(|e| Error::Config(format!("Invalid LOT_METHOD: {}", e)))?;
+        let price_sample_secs = parse_var("PRICE_SAMPLE_SECS", DEFAULT_SAMPLE_INTERVAL_SECS)?;
+        let outside_rth = parse_var("OUTSIDE_RTH", false)?;
+        let outside_rth_policy = parse_var("OUTSIDE_RTH_MARKET_ORDERS", OutsideRthPolicy::Block)?;
+        let policy_path = env::var("POLICY_PATH").unwrap_or_else(|_| "policy.json".to_string());
+        let pnl_tolerance = parse_var("PNL_TOLERANCE", 1.0)?;
+        let account = env::var("ACCOUNT").ok().filter(|account| !account.is_empty());
+        let fa_group = env::var("FA_GROUP").ok().filter(|group| !group.is_empty());
+        let fa_method = match env::var("FA_METHOD") {
+            Ok(method) => Some(
+                method
+                    .parse::<FaMethod>()
+                    .map_err(|e| Error::Config(format!("Invalid FA_METHOD: {}", e)))?,
+            ),
+            Err(_) => None,
+        };
+
+        let bar_intervals = match env::var("BAR_INTERVALS") {
+            Ok(intervals) => intervals
+                .split(',')
+                .filter(|interval| !interval.trim().is_empty())
+                .map(str::parse::<BarInterval>)
+                .collect::<Result<Vec<_>>>()
+                .map_err(|e| Error::Config(format!("Invalid BAR_INTERVALS: {}", e)))?,
+            Err(_) => vec![BarInterval::Time(60), BarInterval::Time(300)],
+        };
+        let realtime_bars = parse_var("REALTIME_BARS", true)?;
+        let atr_period = parse_var("ATR_PERIOD", 14)?;
+        let atr_stop_multiple = match env::var("ATR_STOP_MULTIPLE") {
+            Ok(value) => Some(
+                value
+                    .parse::<f64>()
+                    .map_err(|e| Error::Config(format!("Invalid ATR_STOP_MULTIPLE: {}", e)))?,
+            ),
+            Err(_) => None,
+        };
+        let atr_target_multiple = match env::var("ATR_TARGET_MULTIPLE") {
+            Ok(value) => Some(
+                value
+                    .parse::<f64>()
+                    .map_err(|e| Error::Config(format!("Invalid ATR_TARGET_MULTIPLE: {}", e)))?,
+            ),
+            Err(_) => None,
+        };
+        sizing_mode
+            .check_stop_source(atr_stop_multiple)
+            .map_err(|e| Error::Config(format!("Invalid SIZING_MODE: {}", e)))?;
+        let depth_rows = parse_var("DEPTH_ROWS", 10)?;
+        let depth_warn_levels = parse_var("DEPTH_WARN_LEVELS", 5)?;
         
         Ok(Config {
             tws_host,
@@ -36,10 +213,57 @@ impl Config {
             client_id,
             paper_trading,
             log_level,
+            sizing_mode,
+            risk_limits,
+            daily_loss_limit,
+            flatten_on_loss_limit,
+            audit_log_path,
+            kill_switch_state_path,
+            journal_path,
+            bar_cache_path,
+            watchlist,
+            lot_method,
+            price_sample_secs,
+            outside_rth,
+            outside_rth_policy,
+            policy_path,
+            pnl_tolerance,
+            account,
+            fa_group,
+            fa_method,
+            bar_intervals,
+            realtime_bars,
+            atr_period,
+            atr_stop_multiple,
+            atr_target_multiple,
+            depth_rows,
+            depth_warn_levels,
         })
     }
     
     pub fn connection_url(&self) -> String {
         format!("{}:{}", self.tws_host, self.tws_port)
     }
-}
\ No newline at end of file
+}
+
+/// Splits a comma or space separated symbol list, uppercased.
+pub fn parse_symbols(symbols: &str) -> Vec<String> {
+    symbols
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|symbol| !symbol.is_empty())
+        .map(|symbol| symbol.to_uppercase())
+        .collect()
+}
+
+fn parse_var<T>(name: &str, default: T) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    match env::var(name) {
+        Ok(value) => value
+            .parse::<T>()
+            .map_err(|e| Error::Config(format!("Invalid {}: {}", name, e))),
+        Err(_) => Ok(default),
+    }
+}
@@ -0,0 +1,56 @@
+use crate::account;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use ibapi::Client;
+
+pub struct Connection {
+    client: Client,
+    url: String,
+    accounts: Vec<String>,
+    account: String,
+}
+
+impl Connection {
+    pub fn connect(config: &Config) -> Result<Self> {
+        let url = config.connection_url();
+        let client = Client::connect(&url, config.client_id).map_err(|e| {
+            Error::Connection(format!("connection to TWS at {} failed: {}", url, e))
+        })?;
+        let accounts = client
+            .managed_accounts()
+            .map_err(|e| Error::Connection(format!("listing managed accounts failed: {}", e)))?;
+        let account = account::select_account(&accounts, config.account.as_deref())?;
+        account::check_trading_mode(&account, config.paper_trading)?;
+        Ok(Connection {
+            client,
+            url,
+            accounts,
+            account,
+        })
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Every account this login manages; more than one for FA and linked
+    /// accounts.
+    pub fn accounts(&self) -> &[String] {
+        &self.accounts
+    }
+
+    /// The account trades and orders are attached to.
+    pub fn account(&self) -> &str {
+        &self.account
+    }
+
+    /// Closes the TWS session; ibapi shuts the socket down when the client
+    /// is dropped.
+    pub fn disconnect(self) {
+        drop(self.client);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::error::Result;
+use crate::ui::{format_money, Line};
+use crossterm::style::Color;
+use ibapi::client::Subscription;
+use ibapi::contracts::Contract;
+use ibapi::market_data::realtime::MarketDepths;
+use ibapi::orders::Action;
+use ibapi::Client;
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Bid => write!(f, "bid"),
+            Side::Ask => write!(f, "ask"),
+        }
+    }
+}
+
+/// How a depth message changes a row of the book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// One row of the book.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DepthLevel {
+    pub price: f64,
+    pub size: f64,
+    /// The exchange with smart depth, else the market maker.
+    pub market_maker: String,
+}
+
+/// Bids and asks by row, best first, as IB numbers them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OrderBook {
+    pub bids: Vec<DepthLevel>,
+    pub asks: Vec<DepthLevel>,
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        OrderBook::default()
+    }
+
+    pub fn side(&self, side: Side) -> &[DepthLevel] {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    /// Applies a change to the row at `position`. Updates to rows never
+    /// inserted add them, and deletes of missing rows are ignored, so a
+    /// message lost while resubscribing can't wedge the book.
+    pub fn apply(&mut self, side: Side, position: usize, operation: Operation, level: DepthLevel) {
+        let rows = match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+        match operation {
+            Operation::Insert => rows.insert(position.min(rows.len()), level),
+            Operation::Update if position < rows.len() => rows[position] = level,
+            Operation::Update => rows.push(level),
+            Operation::Delete if position < rows.len() => {
+                rows.remove(position);
+            }
+            Operation::Delete => {}
+        }
+    }
+
+    /// Applies an L1 or L2 depth message. IB codes side 0 as ask and 1 as
+    /// bid, and operations 0, 1 and 2 as insert, update and delete; rows
+    /// with other codes are skipped.
+    pub fn on_depth(&mut self, depth: &MarketDepths) {
+        let (position, operation, side, price, size, market_maker) = match depth {
+            MarketDepths::MarketDepth(depth) => (
+                depth.position,
+                depth.operation,
+                depth.side,
+                depth.price,
+                depth.size,
+                String::new(),
+            ),
+            MarketDepths::MarketDepthL2(depth) => (
+                depth.position,
+                depth.operation,
+                depth.side,
+                depth.price,
+                depth.size,
+                depth.market_maker.clone(),
+            ),
+            MarketDepths::Notice(_) => return,
+        };
+        let side = match side {
+            0 => Side::Ask,
+            1 => Side::Bid,
+            _ => return,
+        };
+        let operation = match operation {
+            0 => Operation::Insert,
+            1 => Operation::Update,
+            2 => Operation::Delete,
+            _ => return,
+        };
+        let Ok(position) = usize::try_from(position) else {
+            return;
+        };
+        self.apply(
+            side,
+            position,
+            operation,
+            DepthLevel {
+                price,
+                size,
+                market_maker,
+            },
+        );
+    }
+
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bids.first().map(|level| level.price)
+    }
+
+    pub fn best_ask(&self) -> Option<f64> {
+        self.asks.first().map(|level| level.price)
+    }
+
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.best_ask()? - self.best_bid()?)
+    }
+
+    /// Shares shown on the top `levels` rows of one side.
+    pub fn visible_size(&self, side: Side, levels: usize) -> f64 {
+        self.side(side)
+            .iter()
+            .take(levels)
+            .map(|level| level.size)
+            .sum()
+    }
+}
+
+/// A market order bigger than the shares shown near the touch, which would
+/// walk the book.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LiquidityWarning {
+    pub side: Side,
+    pub shares: i32,
+    pub visible: f64,
+    pub levels: usize,
+    pub spread: Option<f64>,
+}
+
+impl fmt::Display for LiquidityWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} shares is more than the {:.0} on the top {} {} levels",
+            self.shares, self.visible, self.levels, self.side
+        )?;
+        match self.spread {
+            Some(spread) => write!(f, ", spread {}", format_money(spread)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Warns when a market order of `shares` would take more than the top
+/// `levels` of the side it trades against. An empty side means there is no
+/// depth to judge by, so it doesn't warn.
+pub fn check_liquidity(
+    book: &OrderBook,
+    action: Action,
+    shares: i32,
+    levels: usize,
+) -> Option<LiquidityWarning> {
+    let side = match action {
+        Action::Buy => Side::Ask,
+        _ => Side::Bid,
+    };
+    if book.side(side).is_empty() {
+        return None;
+    }
+    let visible = book.visible_size(side, levels);
+    (f64::from(shares) > visible).then(|| LiquidityWarning {
+        side,
+        shares,
+        visible,
+        levels,
+        spread: book.spread(),
+    })
+}
+
+/// The depth ladder: bids and asks side by side, best first, with the
+/// spread and any warning for the order the selected row would send.
+pub fn depth_lines(
+    symbol: &str,
+    book: &OrderBook,
+    warning: Option<&LiquidityWarning>,
+) -> Vec<Line> {
+    let spread = book.spread().map_or("-".to_string(), format_money);
+    let mut lines = vec![Line::plain(format!("Depth {}  Spread {}", symbol, spread))];
+    if book.bids.is_empty() && book.asks.is_empty() {
+        lines.push(Line::plain("  No depth yet"));
+        return lines;
+    }
+
+    lines.push(Line::plain(format!(
+        "  {:>8} {:>10} | {:<10} {:<8}",
+        "Size", "Bid", "Ask", "Size"
+    )));
+    let price =
+        |level: Option<&DepthLevel>| level.map_or(String::new(), |l| format!("{:.2}", l.price));
+    let size =
+        |level: Option<&DepthLevel>| level.map_or(String::new(), |l| format!("{:.0}", l.size));
+    for row in 0..book.bids.len().max(book.asks.len()) {
+        let bid = book.bids.get(row);
+        let ask = book.asks.get(row);
+        lines.push(Line::plain(format!(
+            "  {:>8} {:>10} | {:<10} {:<8}",
+            size(bid),
+            price(bid),
+            price(ask),
+            size(ask)
+        )));
+    }
+    if let Some(warning) = warning {
+        lines.push(Line::colored(format!("  {}", warning), Color::Yellow));
+    }
+    lines
+}
+
+/// Keeps the order book of one contract from IB's depth stream.
+pub struct DepthFeed<'a> {
+    subscription: Subscription<'a, MarketDepths>,
+    pub book: OrderBook,
+}
+
+impl<'a> DepthFeed<'a> {
+    /// Smart depth aggregates the exchanges a SMART order can route to.
+    pub fn subscribe(client: &'a Client, contract: &Contract, rows: i32) -> Result<Self> {
+        Ok(DepthFeed {
+            subscription: client.market_depth(contract, rows, true)?,
+            book: OrderBook::new(),
+        })
+    }
+
+    /// Applies the updates received so far and returns IB's notices, such
+    /// as a missing depth subscription.
+    pub fn poll(&mut self) -> Vec<String> {
+        let mut notices = Vec::new();
+        while let Some(depth) = self.subscription.try_next() {
+            match &depth {
+                MarketDepths::Notice(notice) => notices.push(notice.message.clone()),
+                depth => self.book.on_depth(depth),
+            }
+        }
+        notices
+    }
+
+    pub fn cancel(&self) {
+        self.subscription.cancel();
+    }
+}
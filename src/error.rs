@@ -14,12 +14,30 @@ pub enum Error {
     #[error("Position error: {0}")]
     Position(String),
     
+    #[error("Sizing error: {0}")]
+    Sizing(String),
+    
+    #[error("Risk check failed: {0}")]
+    Risk(String),
+    
     #[error("Configuration error: {0}")]
     Config(String),
     
+    #[error("Journal error: {0}")]
+    Journal(String),
+    
     #[error("TWS API error: {0}")]
     TwsApi(#[from] ibapi::Error),
     
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     
@@ -30,4 +48,23 @@ pub enum Error {
     Other(String),
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Process exit status for headless runs, so scripts can tell the
+    /// failures apart: 2 order rejected, 3 risk or policy violation,
+    /// 4 connection failure, 1 anything else. Success is 0.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Order(_) | Error::TwsApi(ibapi::Error::Message(..)) => 2,
+            Error::Risk(_) => 3,
+            Error::Connection(_)
+            | Error::TwsApi(
+                ibapi::Error::ConnectionFailed
+                | ibapi::Error::ConnectionReset
+                | ibapi::Error::Shutdown,
+            ) => 4,
+            _ => 1,
+        }
+    }
+}
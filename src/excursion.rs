@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_SAMPLE_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PricePoint {
+    pub price: f64,
+    pub at: DateTime<Utc>,
+}
+
+/// An extreme of unrealized PnL while the trade was held.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ExcursionPoint {
+    pub pnl: f64,
+    pub price: f64,
+    pub at: DateTime<Utc>,
+}
+
+/// Maximum favorable and adverse excursion of a held position, plus the
+/// price path sampled every `sample_interval_secs`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Excursion {
+    pub sample_interval_secs: u64,
+    pub max_favorable: Option<ExcursionPoint>,
+    pub max_adverse: Option<ExcursionPoint>,
+    pub path: Vec<PricePoint>,
+}
+
+impl Default for Excursion {
+    fn default() -> Self {
+        Excursion::new(DEFAULT_SAMPLE_INTERVAL_SECS)
+    }
+}
+
+impl Excursion {
+    pub fn new(sample_interval_secs: u64) -> Self {
+        Excursion {
+            sample_interval_secs,
+            max_favorable: None,
+            max_adverse: None,
+            path: Vec::new(),
+        }
+    }
+
+    /// Clears the recorded excursions, keeping the sample interval.
+    pub fn reset(&mut self) {
+        *self = Excursion::new(self.sample_interval_secs);
+    }
+
+    pub fn record(&mut self, price: f64, pnl: f64, at: DateTime<Utc>) {
+        let point = ExcursionPoint { pnl, price, at };
+        if self.max_favorable.map_or(pnl > 0.0, |best| pnl > best.pnl) {
+            self.max_favorable = Some(point);
+        }
+        if self.max_adverse.map_or(pnl < 0.0, |worst| pnl < worst.pnl) {
+            self.max_adverse = Some(point);
+        }
+
+        let due = self
+            .path
+            .last()
+            .is_none_or(|last| (at - last.at).num_seconds() >= self.sample_interval_secs as i64);
+        if due {
+            self.path.push(PricePoint { price, at });
+        }
+    }
+
+    /// Best unrealized PnL reached, zero if the trade never went green.
+    pub fn mfe(&self) -> f64 {
+        self.max_favorable.map_or(0.0, |point| point.pnl)
+    }
+
+    /// Worst unrealized PnL reached, zero if the trade never went red.
+    pub fn mae(&self) -> f64 {
+        self.max_adverse.map_or(0.0, |point| point.pnl)
+    }
+}
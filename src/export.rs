@@ -0,0 +1,339 @@
+use crate::error::{Error, Result};
+use crate::journal::{Fill, Journal, TradeFilter, TradeRecord};
+use crate::lots::{ClosedLot, GainSummary, LotLedger, LotMethod, Term, WashSaleCandidate};
+use crate::trade::Stage;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One row per trade with the selected columns.
+    Csv,
+    /// Form 8949 style rows accepted by common tax-lot import tools.
+    TaxLots,
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "tax-lots" | "8949" => Ok(ExportFormat::TaxLots),
+            other => Err(Error::Other(format!("Unknown export format: {}", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportColumn {
+    TradeId,
+    Symbol,
+    Strategy,
+    Shares,
+    EntryPrice,
+    ExitPrice,
+    OpenedAt,
+    ClosedAt,
+    HoldDuration,
+    Fills,
+    Commission,
+    RealizedPnl,
+    NetPnl,
+}
+
+impl ExportColumn {
+    pub const ALL: &'static [ExportColumn] = &[
+        ExportColumn::TradeId,
+        ExportColumn::Symbol,
+        ExportColumn::Strategy,
+        ExportColumn::Shares,
+        ExportColumn::EntryPrice,
+        ExportColumn::ExitPrice,
+        ExportColumn::OpenedAt,
+        ExportColumn::ClosedAt,
+        ExportColumn::HoldDuration,
+        ExportColumn::Fills,
+        ExportColumn::Commission,
+        ExportColumn::RealizedPnl,
+        ExportColumn::NetPnl,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportColumn::TradeId => "trade_id",
+            ExportColumn::Symbol => "symbol",
+            ExportColumn::Strategy => "strategy",
+            ExportColumn::Shares => "shares",
+            ExportColumn::EntryPrice => "entry_price",
+            ExportColumn::ExitPrice => "exit_price",
+            ExportColumn::OpenedAt => "opened_at",
+            ExportColumn::ClosedAt => "closed_at",
+            ExportColumn::HoldDuration => "hold_seconds",
+            ExportColumn::Fills => "fills",
+            ExportColumn::Commission => "commission",
+            ExportColumn::RealizedPnl => "realized_pnl",
+            ExportColumn::NetPnl => "net_pnl",
+        }
+    }
+}
+
+impl fmt::Display for ExportColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ExportColumn {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        ExportColumn::ALL
+            .iter()
+            .find(|column| column.name() == s)
+            .copied()
+            .ok_or_else(|| Error::Other(format!("Unknown export column: {}", s)))
+    }
+}
+
+/// A completed trade with its fills, as written to the export.
+#[derive(Debug, Clone)]
+pub struct CompletedTrade {
+    pub record: TradeRecord,
+    pub fills: Vec<Fill>,
+}
+
+impl CompletedTrade {
+    pub fn closed_at(&self) -> DateTime<Utc> {
+        self.record.closed_at.unwrap_or(self.record.opened_at)
+    }
+
+    pub fn commission(&self) -> f64 {
+        self.fills.iter().filter_map(|fill| fill.commission).sum()
+    }
+
+    fn side_commission(&self, side: &str) -> f64 {
+        self.fills
+            .iter()
+            .filter(|fill| fill.side == side)
+            .filter_map(|fill| fill.commission)
+            .sum()
+    }
+
+    pub fn realized_pnl(&self) -> f64 {
+        self.record.realized_pnl.unwrap_or_default()
+    }
+
+    pub fn net_pnl(&self) -> f64 {
+        self.realized_pnl() - self.commission()
+    }
+
+    pub fn hold_seconds(&self) -> i64 {
+        (self.closed_at() - self.record.opened_at).num_seconds()
+    }
+
+    fn value(&self, column: ExportColumn) -> String {
+        let record = &self.record;
+        match column {
+            ExportColumn::TradeId => record.id.to_string(),
+            ExportColumn::Symbol => record.symbol.clone(),
+            ExportColumn::Strategy => record.strategy.clone().unwrap_or_default(),
+            ExportColumn::Shares => record.shares.to_string(),
+            ExportColumn::EntryPrice => format!("{:.4}", record.entry_price),
+            ExportColumn::ExitPrice => record
+                .exit_price
+                .map(|price| format!("{:.4}", price))
+                .unwrap_or_default(),
+            ExportColumn::OpenedAt => record.opened_at.to_rfc3339(),
+            ExportColumn::ClosedAt => self.closed_at().to_rfc3339(),
+            ExportColumn::HoldDuration => self.hold_seconds().to_string(),
+            ExportColumn::Fills => self.fills.len().to_string(),
+            ExportColumn::Commission => format!("{:.2}", self.commission()),
+            ExportColumn::RealizedPnl => format!("{:.2}", self.realized_pnl()),
+            ExportColumn::NetPnl => format!("{:.2}", self.net_pnl()),
+        }
+    }
+}
+
+/// Start of `from` and end of `to` (inclusive dates, UTC).
+fn date_bounds(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+    let from = from.map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    let to = to
+        .and_then(|date| date.succ_opt())
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    (from, to)
+}
+
+/// Loads completed trades closed within `[from, to]` (inclusive dates, UTC).
+pub fn completed_trades(
+    journal: &Journal,
+    filter: &TradeFilter,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<CompletedTrade>> {
+    let (from, to) = date_bounds(from, to);
+
+    let mut trades = Vec::new();
+    for record in journal.query_trades(filter)? {
+        let Some(closed_at) = record.closed_at else {
+            continue;
+        };
+        if record.stage != Stage::Close
+            || from.is_some_and(|from| closed_at < from)
+            || to.is_some_and(|to| closed_at >= to)
+        {
+            continue;
+        }
+        let fills = journal.fills(record.id)?;
+        trades.push(CompletedTrade { record, fills });
+    }
+    trades.sort_by_key(|trade| trade.closed_at());
+    Ok(trades)
+}
+
+/// Lots sold within `[from, to]` (inclusive dates, UTC), matched by a
+/// ledger replayed from the whole journal so earlier buys are in it.
+#[derive(Debug, Clone, Default)]
+pub struct TaxLots {
+    pub lots: Vec<ClosedLot>,
+    pub wash_sales: Vec<WashSaleCandidate>,
+}
+
+impl TaxLots {
+    pub fn gains(&self) -> GainSummary {
+        GainSummary::of(&self.lots)
+    }
+
+    fn is_wash_sale(&self, lot: &ClosedLot) -> bool {
+        self.wash_sales
+            .iter()
+            .any(|sale| sale.sold_lot_id == lot.lot_id && sale.sold_at == lot.sold_at)
+    }
+}
+
+/// Matches the journal's sales to lots with `method`. Lots belong to a
+/// symbol rather than a strategy, so the filter's strategy is ignored.
+pub fn tax_lots(
+    journal: &Journal,
+    filter: &TradeFilter,
+    method: LotMethod,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<TaxLots> {
+    let filter = TradeFilter {
+        strategy: None,
+        ..filter.clone()
+    };
+    let ledger = LotLedger::from_journal(journal, &filter, method)?;
+    let (from, to) = date_bounds(from, to);
+    let within =
+        |at: DateTime<Utc>| from.is_none_or(|from| at >= from) && to.is_none_or(|to| at < to);
+
+    let lots: Vec<ClosedLot> = ledger
+        .closed_lots()
+        .iter()
+        .filter(|lot| within(lot.sold_at))
+        .cloned()
+        .collect();
+    let wash_sales = ledger
+        .wash_sale_candidates()
+        .into_iter()
+        .filter(|sale| within(sale.sold_at))
+        .collect();
+    Ok(TaxLots { lots, wash_sales })
+}
+
+pub fn write_csv<W: Write>(
+    writer: W,
+    trades: &[CompletedTrade],
+    columns: &[ExportColumn],
+) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(columns.iter().map(|column| column.name()))?;
+    for trade in trades {
+        writer.write_record(columns.iter().map(|column| trade.value(*column)))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes Form 8949 style rows: description, acquired and sold dates
+/// (MM/DD/YYYY), proceeds net of sell commissions, cost basis including buy
+/// commissions, the resulting gain or loss, its term, and `W` on losses
+/// flagged as wash-sale candidates. Long positions get one row per matched
+/// lot; short trades open no lots and get one row per trade.
+pub fn write_tax_lots<W: Write>(
+    writer: W,
+    trades: &[CompletedTrade],
+    tax_lots: &TaxLots,
+) -> Result<()> {
+    let mut rows = Vec::new();
+    for lot in &tax_lots.lots {
+        rows.push((
+            lot.sold_at,
+            [
+                format!("{} sh {}", lot.shares, lot.symbol),
+                lot.acquired_at.format("%m/%d/%Y").to_string(),
+                lot.sold_at.format("%m/%d/%Y").to_string(),
+                format!("{:.2}", lot.proceeds),
+                format!("{:.2}", lot.cost_basis),
+                format!("{:.2}", lot.gain()),
+                term_name(lot.term).to_string(),
+                if tax_lots.is_wash_sale(lot) { "W" } else { "" }.to_string(),
+            ],
+        ));
+    }
+    // Short trades are acquired when covered and sold when opened
+    for trade in trades.iter().filter(|trade| trade.record.shares < 0) {
+        let record = &trade.record;
+        let shares = record.shares.abs() as f64;
+        let Some(exit_price) = record.exit_price else {
+            continue;
+        };
+        let proceeds = record.entry_price * shares - trade.side_commission("SLD");
+        let cost_basis = exit_price * shares + trade.side_commission("BOT");
+        rows.push((
+            trade.closed_at(),
+            [
+                format!("{} sh {}", shares, record.symbol),
+                trade.closed_at().format("%m/%d/%Y").to_string(),
+                record.opened_at.format("%m/%d/%Y").to_string(),
+                format!("{:.2}", proceeds),
+                format!("{:.2}", cost_basis),
+                format!("{:.2}", proceeds - cost_basis),
+                term_name(Term::ShortTerm).to_string(),
+                String::new(),
+            ],
+        ));
+    }
+    rows.sort_by_key(|(closed_at, _)| *closed_at);
+
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "Description",
+        "Date Acquired",
+        "Date Sold",
+        "Proceeds",
+        "Cost Basis",
+        "Gain or Loss",
+        "Term",
+        "Wash Sale",
+    ])?;
+    for (_, row) in rows {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn term_name(term: Term) -> &'static str {
+    match term {
+        Term::ShortTerm => "Short",
+        Term::LongTerm => "Long",
+    }
+}
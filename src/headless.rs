@@ -0,0 +1,292 @@
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::excursion::Excursion;
+use crate::journal::{self, Fill, Journal};
+use crate::orders::OrderManager;
+use crate::quote;
+use crate::recovery;
+use crate::sizing::{self, SizingMode};
+use crate::trade::{Stage, Trade};
+use ibapi::orders::{Action, PlaceOrder};
+use ibapi::Client;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How long to wait for IB to confirm the cancel of an order that timed out.
+const CANCEL_GRACE: Duration = Duration::from_secs(5);
+
+/// Rules for orders placed without a prompt, read from a JSON policy file.
+/// A missing file means the defaults: every order needs `--yes`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    /// Place headless orders without `--yes`.
+    pub auto_confirm: bool,
+    /// Symbols headless orders may trade; empty allows any.
+    pub symbols: Vec<String>,
+    pub max_quantity: Option<i32>,
+    pub max_notional: Option<f64>,
+    /// Refuse headless market orders.
+    pub require_limit: bool,
+}
+
+impl Policy {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Policy::default());
+        }
+        let text = fs::read_to_string(path)?;
+        serde_json::from_str(&text)
+            .map_err(|e| Error::Config(format!("Invalid policy file {}: {}", path.display(), e)))
+    }
+
+    /// Refusals are risk errors, so scripts see them under the same exit code
+    /// as the risk engine's.
+    pub fn check(&self, request: &OrderRequest, price: f64, confirmed: bool) -> Result<()> {
+        if !confirmed && !self.auto_confirm {
+            return Err(Error::Risk(
+                "headless orders need --yes or auto_confirm in the policy file".to_string(),
+            ));
+        }
+        if !self.symbols.is_empty() && !self.symbols.contains(&request.symbol) {
+            return Err(Error::Risk(format!(
+                "{} is not in the policy's symbol list",
+                request.symbol
+            )));
+        }
+        if self.require_limit && request.limit_price.is_none() {
+            return Err(Error::Risk(
+                "policy requires a limit price for headless orders".to_string(),
+            ));
+        }
+        if let (Some(max), Some(quantity)) = (self.max_quantity, request.quantity) {
+            if quantity > max {
+                return Err(Error::Risk(format!(
+                    "{} shares exceeds the policy maximum of {}",
+                    quantity, max
+                )));
+            }
+        }
+        if let (Some(max), Some(quantity)) = (self.max_notional, request.quantity) {
+            let notional = quantity as f64 * request.limit_price.unwrap_or(price);
+            if notional > max {
+                return Err(Error::Risk(format!(
+                    "${:.2} notional exceeds the policy maximum of ${:.2}",
+                    notional, max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderRequest {
+    pub symbol: String,
+    pub action: Action,
+    /// Shares to trade. Buys fall back to the sizing mode, sells to the
+    /// whole position.
+    pub quantity: Option<i32>,
+    pub limit_price: Option<f64>,
+    /// How long to wait for the fill; `None` returns once the order is sent.
+    pub wait_fill: Option<Duration>,
+}
+
+/// What a headless order did, printed as the command's result.
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderOutcome {
+    pub order_id: i32,
+    pub symbol: String,
+    pub action: String,
+    pub quantity: i32,
+    pub limit_price: Option<f64>,
+    pub status: String,
+    pub filled: f64,
+    pub average_fill_price: Option<f64>,
+    pub trade: Trade,
+}
+
+/// Places one order for `account` without prompting: policy and risk
+/// checks, then the order, journaled like an interactive one. An order
+/// still working when `wait_fill` runs out is cancelled. An order
+/// cancelled after a partial fill returns its outcome with the shares that
+/// filled; one cancelled before any fill is an error.
+pub fn place(
+    client: &Client,
+    config: &Config,
+    journal: &Journal,
+    account: &str,
+    policy: &Policy,
+    request: &OrderRequest,
+    confirmed: bool,
+) -> Result<OrderOutcome> {
+    let mut trade = match request.action {
+        Action::Buy => {
+            let mut trade = Trade::new(request.symbol.clone());
+            trade.account = Some(account.to_string());
+            trade.excursion = Excursion::new(config.price_sample_secs);
+            trade.create_contract();
+            trade
+        }
+        _ => recovery::recover(client, journal)?
+            .trades
+            .into_iter()
+            .find(|trade| {
+                trade.symbol == request.symbol
+                    && trade.stage == Stage::Hold
+                    && trade.account.as_deref().is_none_or(|held| held == account)
+            })
+            .ok_or_else(|| Error::Position(format!("{}: no open trade to sell", request.symbol)))?,
+    };
+    trade.resolve_contract(client)?;
+    let contract = trade
+        .contract
+        .clone()
+        .ok_or_else(|| Error::Order(format!("{}: contract not created", trade.symbol)))?;
+    let price = quote::snapshot(client, &contract)?.price().ok_or_else(|| {
+        Error::MarketData(format!(
+            "{}: no price in market data snapshot",
+            trade.symbol
+        ))
+    })?;
+    trade.update_price(price);
+
+    let shares = match (request.action, request.quantity) {
+        (Action::Buy, Some(quantity)) => quantity,
+        // Selling more than is held would open a short
+        (_, Some(quantity)) if quantity > trade.position.abs() => {
+            return Err(Error::Position(format!(
+                "{}: selling {} shares but only {} held",
+                trade.symbol,
+                quantity,
+                trade.position.abs()
+            )));
+        }
+        (_, Some(quantity)) => quantity,
+        (Action::Buy, None) => {
+            let net_liquidation = match config.sizing_mode {
+                SizingMode::PercentOfNetLiq(_) => Some(sizing::net_liquidation(client, account)?),
+                _ => None,
+            };
+            trade.shares_to_buy(&config.sizing_mode, net_liquidation)?
+        }
+        (_, None) => trade.position.abs(),
+    };
+    let sized = OrderRequest {
+        quantity: Some(shares),
+        ..request.clone()
+    };
+    policy.check(&sized, trade.current_price, confirmed)?;
+
+    let mut orders = OrderManager::new(client, config)?;
+    let (order_id, order, subscription) =
+        orders.submit(&trade, request.action, shares, request.limit_price)?;
+    if request.action == Action::Buy {
+        trade.stage = Stage::Open;
+        journal.open_trade(&mut trade)?;
+    }
+    let trade_id = journal::journal_id(&trade)?;
+    journal.record_order(trade_id, order_id, &order)?;
+
+    let mut outcome = OrderOutcome {
+        order_id,
+        symbol: trade.symbol.clone(),
+        action: request.action.to_string(),
+        quantity: shares,
+        limit_price: request.limit_price,
+        status: "Submitted".to_string(),
+        filled: 0.0,
+        average_fill_price: None,
+        trade: trade.clone(),
+    };
+    let Some(wait) = request.wait_fill else {
+        return Ok(outcome);
+    };
+
+    let mut deadline = Instant::now() + wait;
+    let mut timed_out = false;
+    while outcome.status != "Filled" {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() && !timed_out {
+            // Don't leave the order working once nobody is waiting on it
+            client.cancel_order(order_id, "")?;
+            timed_out = true;
+            deadline = Instant::now() + CANCEL_GRACE;
+            continue;
+        }
+        if remaining.is_zero() {
+            return Err(Error::Order(format!(
+                "order #{} not filled within {}s and its cancel is unconfirmed, it may still be working ({} of {} shares filled)",
+                order_id,
+                wait.as_secs(),
+                outcome.filled,
+                shares
+            )));
+        }
+        let Some(event) = subscription.next_timeout(remaining) else {
+            continue;
+        };
+        match event {
+            PlaceOrder::OrderStatus(status) => {
+                journal.update_order_status(order_id, &status.status)?;
+                outcome.status = status.status.clone();
+                outcome.filled = status.filled;
+                if status.filled > 0.0 {
+                    outcome.average_fill_price = Some(status.average_fill_price);
+                }
+                if matches!(
+                    status.status.as_str(),
+                    "Cancelled" | "ApiCancelled" | "Inactive"
+                ) {
+                    // Shares that filled before the cancel still count
+                    if status.filled > 0.0 {
+                        break;
+                    }
+                    if trade.stage == Stage::Open {
+                        trade.stage = Stage::Close;
+                        journal.update_stage(&trade)?;
+                    }
+                    if timed_out {
+                        return Err(Error::Order(format!(
+                            "order #{} not filled within {}s, cancelled",
+                            order_id,
+                            wait.as_secs()
+                        )));
+                    }
+                    return Err(Error::Order(format!(
+                        "order #{} {}",
+                        order_id,
+                        status.status.to_lowercase()
+                    )));
+                }
+            }
+            PlaceOrder::ExecutionData(data) => {
+                journal.record_fill(trade_id, &Fill::from_execution(&data))?;
+            }
+            PlaceOrder::CommissionReport(report) => journal.record_commission(&report)?,
+            PlaceOrder::Message(notice) => {
+                tracing::warn!("order #{}: {}", order_id, notice.message);
+            }
+            PlaceOrder::OpenOrder(_) => {}
+        }
+    }
+
+    let price = outcome.average_fill_price.unwrap_or(trade.current_price);
+    if request.action == Action::Buy {
+        trade.open_position(outcome.filled as i32, price);
+        journal.update_entry(&trade)?;
+    } else if outcome.filled as i32 >= trade.position.abs() {
+        trade.update_price(price);
+        trade.close_position();
+        journal.close_trade(&trade)?;
+    } else if outcome.filled > 0.0 {
+        trade.update_price(price);
+        trade.reduce_position(outcome.filled as i32);
+        journal.update_position(&trade)?;
+    }
+    outcome.trade = trade;
+    Ok(outcome)
+}
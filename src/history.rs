@@ -0,0 +1,474 @@
+use crate::error::{Error, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use ibapi::contracts::Contract;
+use ibapi::market_data::historical::{self, BarSize, WhatToShow};
+use ibapi::Client;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+
+/// IB's historical data pacing rules: no more than 60 requests in ten
+/// minutes, and no more than five for one contract within two seconds.
+const PACING_LIMITS: &[(usize, Duration)] =
+    &[(5, Duration::from_secs(2)), (60, Duration::from_secs(600))];
+
+const CACHE_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS bars (
+        contract_id INTEGER NOT NULL,
+        series TEXT NOT NULL,
+        time INTEGER NOT NULL,
+        open REAL NOT NULL,
+        high REAL NOT NULL,
+        low REAL NOT NULL,
+        close REAL NOT NULL,
+        volume REAL NOT NULL,
+        wap REAL NOT NULL,
+        count INTEGER NOT NULL,
+        PRIMARY KEY (contract_id, series, time)
+    );
+    CREATE TABLE IF NOT EXISTS chunks (
+        contract_id INTEGER NOT NULL,
+        series TEXT NOT NULL,
+        start INTEGER NOT NULL,
+        PRIMARY KEY (contract_id, series, start)
+    );";
+
+/// Parses `1s`, `5s`, `15s`, `30s`, `1m`, `2m`, `3m`, `5m`, `15m`, `20m`,
+/// `30m`, `1h`, `2h`, `3h`, `4h`, `8h` or `1d`.
+pub fn parse_bar_size(s: &str) -> Result<BarSize> {
+    match s.trim().to_lowercase().as_str() {
+        "1s" => Ok(BarSize::Sec),
+        "5s" => Ok(BarSize::Sec5),
+        "15s" => Ok(BarSize::Sec15),
+        "30s" => Ok(BarSize::Sec30),
+        "1m" => Ok(BarSize::Min),
+        "2m" => Ok(BarSize::Min2),
+        "3m" => Ok(BarSize::Min3),
+        "5m" => Ok(BarSize::Min5),
+        "15m" => Ok(BarSize::Min15),
+        "20m" => Ok(BarSize::Min20),
+        "30m" => Ok(BarSize::Min30),
+        "1h" => Ok(BarSize::Hour),
+        "2h" => Ok(BarSize::Hour2),
+        "3h" => Ok(BarSize::Hour3),
+        "4h" => Ok(BarSize::Hour4),
+        "8h" => Ok(BarSize::Hour8),
+        "1d" => Ok(BarSize::Day),
+        other => Err(Error::Config(format!("Unknown bar size: {}", other))),
+    }
+}
+
+/// The longest span IB serves in one request for the bar size.
+pub fn chunk_span(bar_size: BarSize) -> TimeDelta {
+    match bar_size {
+        BarSize::Sec => TimeDelta::minutes(30),
+        BarSize::Sec5 => TimeDelta::hours(2),
+        BarSize::Sec15 => TimeDelta::hours(4),
+        BarSize::Sec30 => TimeDelta::hours(8),
+        BarSize::Min => TimeDelta::days(1),
+        BarSize::Min2 => TimeDelta::days(2),
+        BarSize::Min3 | BarSize::Min5 => TimeDelta::weeks(1),
+        BarSize::Min15 => TimeDelta::weeks(2),
+        BarSize::Min20
+        | BarSize::Min30
+        | BarSize::Hour
+        | BarSize::Hour2
+        | BarSize::Hour3
+        | BarSize::Hour4
+        | BarSize::Hour8 => TimeDelta::weeks(4),
+        BarSize::Day | BarSize::Week | BarSize::Month => TimeDelta::weeks(52),
+    }
+}
+
+/// Splits `[from, to)` into request windows of `chunk_span`, aligned to the
+/// Unix epoch so overlapping requests share windows and hit the cache.
+pub fn chunks(
+    bar_size: BarSize,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    if from >= to {
+        return Vec::new();
+    }
+    let span = chunk_span(bar_size).num_seconds();
+    let mut start = from.timestamp().div_euclid(span) * span;
+    let mut windows = Vec::new();
+    while start < to.timestamp() {
+        if let (Some(window_start), Some(window_end)) = (
+            DateTime::from_timestamp(start, 0),
+            DateTime::from_timestamp(start + span, 0),
+        ) {
+            windows.push((window_start, window_end));
+        }
+        start += span;
+    }
+    windows
+}
+
+/// The request duration covering `span`: seconds up to a day, whole days
+/// beyond, as IB rejects longer second durations.
+fn request_duration(span: TimeDelta) -> historical::Duration {
+    let seconds = span.num_seconds().max(1);
+    if seconds <= 86_400 {
+        historical::Duration::seconds(seconds as i32)
+    } else {
+        historical::Duration::days(((seconds + 86_399) / 86_400) as i32)
+    }
+}
+
+/// What the bars are built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarType {
+    Trades,
+    Midpoint,
+    BidAsk,
+}
+
+impl BarType {
+    pub fn what_to_show(&self) -> WhatToShow {
+        match self {
+            BarType::Trades => WhatToShow::Trades,
+            BarType::Midpoint => WhatToShow::MidPoint,
+            BarType::BidAsk => WhatToShow::BidAsk,
+        }
+    }
+}
+
+impl fmt::Display for BarType {
+    /// The `whatToShow` name TWS uses.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.what_to_show())
+    }
+}
+
+impl FromStr for BarType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "trades" => Ok(BarType::Trades),
+            "midpoint" => Ok(BarType::Midpoint),
+            "bid_ask" => Ok(BarType::BidAsk),
+            other => Err(Error::Config(format!("Unknown bar type: {}", other))),
+        }
+    }
+}
+
+/// Which bars of a contract to load.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BarQuery {
+    pub bar_size: BarSize,
+    pub bar_type: BarType,
+    /// Only bars within regular trading hours.
+    pub use_rth: bool,
+}
+
+impl BarQuery {
+    /// Identifies the series in the cache, e.g. `5 mins/TRADES/rth`.
+    pub fn series(&self) -> String {
+        let hours = if self.use_rth { "rth" } else { "all" };
+        format!("{}/{}/{}", self.bar_size, self.bar_type, hours)
+    }
+}
+
+/// One historical bar. For `BidAsk` bars IB sends the average bid as open,
+/// the average ask as close, and the lowest bid and highest ask as low and
+/// high; volume, wap and count are only set for `Trades`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bar {
+    pub time: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub wap: f64,
+    pub count: i32,
+}
+
+impl From<&historical::Bar> for Bar {
+    fn from(bar: &historical::Bar) -> Self {
+        Bar {
+            time: DateTime::from_timestamp(bar.date.unix_timestamp(), 0).unwrap_or_default(),
+            open: bar.open,
+            high: bar.high,
+            low: bar.low,
+            close: bar.close,
+            volume: bar.volume,
+            wap: bar.wap,
+            count: bar.count,
+        }
+    }
+}
+
+/// Spaces historical data requests to stay inside `PACING_LIMITS`, so IB
+/// doesn't answer with pacing violations.
+#[derive(Debug, Default)]
+pub struct Pacer {
+    sent: VecDeque<Instant>,
+}
+
+impl Pacer {
+    pub fn new() -> Self {
+        Pacer::default()
+    }
+
+    /// How long to wait at `now` before the next request may go out.
+    pub fn delay_at(&self, now: Instant) -> Duration {
+        PACING_LIMITS
+            .iter()
+            .filter(|(limit, _)| self.sent.len() >= *limit)
+            .map(|(limit, window)| {
+                let oldest = self.sent[self.sent.len() - limit];
+                (oldest + *window).saturating_duration_since(now)
+            })
+            .max()
+            .unwrap_or_default()
+    }
+
+    pub fn record(&mut self, at: Instant) {
+        self.sent.push_back(at);
+        let (_, longest) = PACING_LIMITS[PACING_LIMITS.len() - 1];
+        while let Some(sent) = self.sent.front() {
+            if at.duration_since(*sent) >= longest {
+                self.sent.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Sleeps until a request is allowed and records it.
+    pub fn wait(&mut self) {
+        let delay = self.delay_at(Instant::now());
+        if !delay.is_zero() {
+            tracing::info!("Pacing historical data requests, waiting {:?}", delay);
+            thread::sleep(delay);
+        }
+        self.record(Instant::now());
+    }
+}
+
+/// Local SQLite store of historical bars. A request window is recorded
+/// once it has been fetched in full, so it is never requested again; the
+/// window still in progress is always refetched.
+pub struct BarCache {
+    conn: Connection,
+}
+
+impl BarCache {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(CACHE_SCHEMA)?;
+        Ok(BarCache { conn })
+    }
+
+    pub fn has_chunk(
+        &self,
+        contract_id: i64,
+        query: &BarQuery,
+        start: DateTime<Utc>,
+    ) -> Result<bool> {
+        let found = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM chunks WHERE contract_id = ?1 AND series = ?2 AND start = ?3",
+                params![contract_id, query.series(), start.timestamp()],
+                |_| Ok(()),
+            )
+            .optional()?;
+        Ok(found.is_some())
+    }
+
+    /// Stores bars, replacing any cached bar with the same time, and marks
+    /// the window starting at `complete_chunk` as fetched.
+    pub fn store(
+        &mut self,
+        contract_id: i64,
+        query: &BarQuery,
+        bars: &[Bar],
+        complete_chunk: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let series = query.series();
+        let tx = self.conn.transaction()?;
+        for bar in bars {
+            tx.execute(
+                "INSERT OR REPLACE INTO bars (contract_id, series, time, open, high, low, close, volume, wap, count)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    contract_id,
+                    series,
+                    bar.time.timestamp(),
+                    bar.open,
+                    bar.high,
+                    bar.low,
+                    bar.close,
+                    bar.volume,
+                    bar.wap,
+                    bar.count,
+                ],
+            )?;
+        }
+        if let Some(start) = complete_chunk {
+            tx.execute(
+                "INSERT OR IGNORE INTO chunks (contract_id, series, start) VALUES (?1, ?2, ?3)",
+                params![contract_id, series, start.timestamp()],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Cached bars with `from <= time < to`, oldest first.
+    pub fn bars(
+        &self,
+        contract_id: i64,
+        query: &BarQuery,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Bar>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT time, open, high, low, close, volume, wap, count FROM bars
+             WHERE contract_id = ?1 AND series = ?2 AND time >= ?3 AND time < ?4
+             ORDER BY time",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                contract_id,
+                query.series(),
+                from.timestamp(),
+                to.timestamp()
+            ],
+            |row| {
+                Ok(Bar {
+                    time: DateTime::from_timestamp(row.get(0)?, 0).unwrap_or_default(),
+                    open: row.get(1)?,
+                    high: row.get(2)?,
+                    low: row.get(3)?,
+                    close: row.get(4)?,
+                    volume: row.get(5)?,
+                    wap: row.get(6)?,
+                    count: row.get(7)?,
+                })
+            },
+        )?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+}
+
+/// Loads the contract's bars with `from <= time < to`. Windows missing from
+/// the cache are requested from TWS one by one, paced, and cached.
+pub fn fetch(
+    client: &Client,
+    cache: &mut BarCache,
+    pacer: &mut Pacer,
+    contract: &Contract,
+    query: &BarQuery,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<Bar>> {
+    if contract.contract_id == 0 {
+        return Err(Error::MarketData(format!(
+            "{}: contract not resolved",
+            contract.symbol
+        )));
+    }
+    let contract_id = contract.contract_id as i64;
+    let now = Utc::now();
+    for (start, end) in chunks(query.bar_size, from, to.min(now)) {
+        if cache.has_chunk(contract_id, query, start)? {
+            continue;
+        }
+        let complete = end <= now;
+        pacer.wait();
+        let bars = request(client, contract, query, start, end.min(now), complete)?;
+        tracing::debug!(
+            "{}: {} {} bars from {}",
+            contract.symbol,
+            bars.len(),
+            query.series(),
+            start
+        );
+        cache.store(contract_id, query, &bars, complete.then_some(start))?;
+    }
+    cache.bars(contract_id, query, from, to)
+}
+
+/// One request for the window ending at `end`. The window still in
+/// progress is requested up to now, as IB wants no end time in the future.
+fn request(
+    client: &Client,
+    contract: &Contract,
+    query: &BarQuery,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    complete: bool,
+) -> Result<Vec<Bar>> {
+    let interval_end = if complete {
+        Some(
+            OffsetDateTime::from_unix_timestamp(end.timestamp())
+                .map_err(|e| Error::MarketData(format!("Invalid bar window end: {}", e)))?,
+        )
+    } else {
+        None
+    };
+    let result = client.historical_data(
+        contract,
+        interval_end,
+        request_duration(end - start),
+        query.bar_size,
+        query.bar_type.what_to_show(),
+        query.use_rth,
+    );
+    match result {
+        Ok(data) => Ok(data.bars.iter().map(Bar::from).collect()),
+        // Weekends, holidays and dates before the listing have no bars
+        Err(ibapi::Error::Message(162, message)) if message.contains("returned no data") => {
+            Ok(Vec::new())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Writes bars as CSV, one row per bar with RFC 3339 times.
+pub fn write_csv<W: Write>(writer: W, bars: &[Bar]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "time", "open", "high", "low", "close", "volume", "wap", "count",
+    ])?;
+    for bar in bars {
+        writer.write_record([
+            bar.time.to_rfc3339(),
+            bar.open.to_string(),
+            bar.high.to_string(),
+            bar.low.to_string(),
+            bar.close.to_string(),
+            bar.volume.to_string(),
+            bar.wap.to_string(),
+            bar.count.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
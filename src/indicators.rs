@@ -0,0 +1,353 @@
+use crate::history::Bar;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// An indicator fed one completed bar at a time, the same way live and in
+/// backtests. Every update is O(1).
+pub trait Indicator {
+    type Output;
+
+    /// Adds a bar and returns the new value once enough bars are in.
+    fn on_bar(&mut self, bar: &Bar) -> Option<Self::Output>;
+}
+
+/// Simple moving average of the last `period` values.
+#[derive(Debug, Clone)]
+pub struct Sma {
+    period: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        Sma {
+            period: period.max(1),
+            window: VecDeque::new(),
+            sum: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        self.window.push_back(value);
+        self.sum += value;
+        if self.window.len() > self.period {
+            self.sum -= self.window.pop_front().unwrap_or_default();
+        }
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        (self.window.len() == self.period).then(|| self.sum / self.period as f64)
+    }
+}
+
+/// Exponential moving average, seeded with the SMA of its first `period`
+/// values.
+#[derive(Debug, Clone)]
+pub struct Ema {
+    alpha: f64,
+    seed: Sma,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        Ema {
+            alpha: 2.0 / (period.max(1) as f64 + 1.0),
+            seed: Sma::new(period),
+            value: None,
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        self.value = match self.value {
+            Some(previous) => Some(previous + self.alpha * (value - previous)),
+            None => self.seed.update(value),
+        };
+        self.value
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+/// Wilder's smoothing: the plain average of the first `period` values,
+/// then `(previous * (period - 1) + value) / period`.
+#[derive(Debug, Clone)]
+struct Wilder {
+    period: usize,
+    count: usize,
+    value: f64,
+}
+
+impl Wilder {
+    fn new(period: usize) -> Self {
+        Wilder {
+            period: period.max(1),
+            count: 0,
+            value: 0.0,
+        }
+    }
+
+    fn update(&mut self, value: f64) -> Option<f64> {
+        let period = self.period as f64;
+        self.count += 1;
+        if self.count <= self.period {
+            self.value += value / period;
+        } else {
+            self.value = (self.value * (period - 1.0) + value) / period;
+        }
+        self.value()
+    }
+
+    fn value(&self) -> Option<f64> {
+        (self.count >= self.period).then_some(self.value)
+    }
+}
+
+/// Relative strength index with Wilder's smoothing, 0 to 100.
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    previous: Option<f64>,
+    gains: Wilder,
+    losses: Wilder,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Rsi {
+            previous: None,
+            gains: Wilder::new(period),
+            losses: Wilder::new(period),
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        let previous = self.previous.replace(value)?;
+        let change = value - previous;
+        self.gains.update(change.max(0.0));
+        self.losses.update((-change).max(0.0));
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        let gain = self.gains.value()?;
+        let loss = self.losses.value()?;
+        if loss == 0.0 {
+            return Some(if gain == 0.0 { 50.0 } else { 100.0 });
+        }
+        Some(100.0 - 100.0 / (1.0 + gain / loss))
+    }
+}
+
+/// Average true range with Wilder's smoothing.
+#[derive(Debug, Clone)]
+pub struct Atr {
+    previous_close: Option<f64>,
+    ranges: Wilder,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Atr {
+            previous_close: None,
+            ranges: Wilder::new(period),
+        }
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.ranges.value()
+    }
+}
+
+impl Indicator for Atr {
+    type Output = f64;
+
+    /// The first bar has no previous close, so its range is high - low.
+    fn on_bar(&mut self, bar: &Bar) -> Option<f64> {
+        let range = match self.previous_close.replace(bar.close) {
+            Some(close) => (bar.high - bar.low)
+                .max((bar.high - close).abs())
+                .max((bar.low - close).abs()),
+            None => bar.high - bar.low,
+        };
+        self.ranges.update(range)
+    }
+}
+
+/// Volume weighted average price since the last `reset`.
+#[derive(Debug, Clone, Default)]
+pub struct Vwap {
+    price_volume: f64,
+    volume: f64,
+}
+
+impl Vwap {
+    pub fn new() -> Self {
+        Vwap::default()
+    }
+
+    /// Adds a trade. Trades without size don't move the average.
+    pub fn update(&mut self, price: f64, volume: f64) -> Option<f64> {
+        if volume > 0.0 {
+            self.price_volume += price * volume;
+            self.volume += volume;
+        }
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        (self.volume > 0.0).then(|| self.price_volume / self.volume)
+    }
+
+    /// Starts a new session.
+    pub fn reset(&mut self) {
+        *self = Vwap::default();
+    }
+}
+
+impl Indicator for Vwap {
+    type Output = f64;
+
+    /// Weights each bar's typical price, (high + low + close) / 3, by its
+    /// volume.
+    fn on_bar(&mut self, bar: &Bar) -> Option<f64> {
+        self.update((bar.high + bar.low + bar.close) / 3.0, bar.volume)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Bands {
+    pub upper: f64,
+    pub middle: f64,
+    pub lower: f64,
+}
+
+/// Bollinger Bands: the SMA of `period` values, `width` population
+/// standard deviations either side.
+#[derive(Debug, Clone)]
+pub struct Bollinger {
+    width: f64,
+    mean: Sma,
+    squares: Sma,
+}
+
+impl Bollinger {
+    pub fn new(period: usize, width: f64) -> Self {
+        Bollinger {
+            width,
+            mean: Sma::new(period),
+            squares: Sma::new(period),
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<Bands> {
+        self.mean.update(value);
+        self.squares.update(value * value);
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<Bands> {
+        let middle = self.mean.value()?;
+        let variance = (self.squares.value()? - middle * middle).max(0.0);
+        let offset = self.width * variance.sqrt();
+        Some(Bands {
+            upper: middle + offset,
+            middle,
+            lower: middle - offset,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MacdValue {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+/// MACD: fast EMA minus slow EMA, with an EMA of that as the signal line.
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+    value: Option<MacdValue>,
+}
+
+impl Macd {
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Self {
+        Macd {
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+            value: None,
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<MacdValue> {
+        let fast = self.fast.update(value);
+        let slow = self.slow.update(value);
+        let macd = fast? - slow?;
+        let signal = self.signal.update(macd)?;
+        self.value = Some(MacdValue {
+            macd,
+            signal,
+            histogram: macd - signal,
+        });
+        self.value
+    }
+
+    pub fn value(&self) -> Option<MacdValue> {
+        self.value
+    }
+}
+
+impl Default for Macd {
+    /// The usual 12, 26, 9.
+    fn default() -> Self {
+        Macd::new(12, 26, 9)
+    }
+}
+
+impl Indicator for Sma {
+    type Output = f64;
+
+    fn on_bar(&mut self, bar: &Bar) -> Option<f64> {
+        self.update(bar.close)
+    }
+}
+
+impl Indicator for Ema {
+    type Output = f64;
+
+    fn on_bar(&mut self, bar: &Bar) -> Option<f64> {
+        self.update(bar.close)
+    }
+}
+
+impl Indicator for Rsi {
+    type Output = f64;
+
+    fn on_bar(&mut self, bar: &Bar) -> Option<f64> {
+        self.update(bar.close)
+    }
+}
+
+impl Indicator for Bollinger {
+    type Output = Bands;
+
+    fn on_bar(&mut self, bar: &Bar) -> Option<Bands> {
+        self.update(bar.close)
+    }
+}
+
+impl Indicator for Macd {
+    type Output = MacdValue;
+
+    fn on_bar(&mut self, bar: &Bar) -> Option<MacdValue> {
+        self.update(bar.close)
+    }
+}
@@ -0,0 +1,525 @@
+use crate::error::{Error, Result};
+use crate::excursion::Excursion;
+use crate::trade::{Stage, Trade};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use ibapi::orders::{CommissionReport, ExecutionData, Order};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Schema migrations, applied in order. The database's `user_version` is the
+/// number of migrations already applied, so only append to this list.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE trades (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        symbol TEXT NOT NULL,
+        contract_id INTEGER NOT NULL,
+        strategy TEXT,
+        shares INTEGER NOT NULL,
+        entry_price REAL NOT NULL,
+        exit_price REAL,
+        realized_pnl REAL,
+        stage TEXT NOT NULL,
+        opened_at TEXT NOT NULL,
+        closed_at TEXT
+    );
+    CREATE INDEX trades_symbol ON trades (symbol);
+    CREATE INDEX trades_opened_at ON trades (opened_at);
+    CREATE TABLE orders (
+        order_id INTEGER PRIMARY KEY,
+        trade_id INTEGER NOT NULL REFERENCES trades (id),
+        action TEXT NOT NULL,
+        quantity INTEGER NOT NULL,
+        limit_price REAL,
+        status TEXT NOT NULL,
+        submitted_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE fills (
+        execution_id TEXT PRIMARY KEY,
+        trade_id INTEGER NOT NULL REFERENCES trades (id),
+        order_id INTEGER NOT NULL,
+        side TEXT NOT NULL,
+        shares REAL NOT NULL,
+        price REAL NOT NULL,
+        commission REAL,
+        filled_at TEXT NOT NULL
+    );
+    CREATE TABLE stage_transitions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        trade_id INTEGER NOT NULL REFERENCES trades (id),
+        stage TEXT NOT NULL,
+        at TEXT NOT NULL
+    );",
+    "ALTER TABLE trades ADD COLUMN stop_price REAL;
+    ALTER TABLE orders ADD COLUMN order_type TEXT NOT NULL DEFAULT 'MKT';
+    ALTER TABLE orders ADD COLUMN aux_price REAL;",
+    "ALTER TABLE trades ADD COLUMN excursion TEXT;",
+    "ALTER TABLE trades ADD COLUMN account TEXT;
+    ALTER TABLE orders ADD COLUMN account TEXT;
+    CREATE INDEX trades_account ON trades (account);",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeRecord {
+    pub id: i64,
+    pub symbol: String,
+    pub contract_id: i64,
+    pub account: Option<String>,
+    pub strategy: Option<String>,
+    pub shares: i32,
+    pub entry_price: f64,
+    pub exit_price: Option<f64>,
+    pub stop_price: Option<f64>,
+    pub realized_pnl: Option<f64>,
+    pub stage: Stage,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fill {
+    pub execution_id: String,
+    pub order_id: i32,
+    pub side: String,
+    pub shares: f64,
+    pub price: f64,
+    pub commission: Option<f64>,
+    pub filled_at: DateTime<Utc>,
+}
+
+impl Fill {
+    pub fn from_execution(data: &ExecutionData) -> Self {
+        Fill {
+            execution_id: data.execution.execution_id.clone(),
+            order_id: data.execution.order_id,
+            side: data.execution.side.clone(),
+            shares: data.execution.shares,
+            price: data.execution.price,
+            commission: None,
+            filled_at: parse_execution_time(&data.execution.time).unwrap_or_else(Utc::now),
+        }
+    }
+}
+
+/// Parses IB's execution time, `20230224  12:04:56` optionally followed by
+/// a time zone such as `US/Eastern`. Without one the time is in the local
+/// zone TWS reports in.
+pub fn parse_execution_time(time: &str) -> Option<DateTime<Utc>> {
+    let mut parts = time.split_whitespace();
+    let (date, clock) = (parts.next()?, parts.next()?);
+    let naive =
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, clock), "%Y%m%d %H:%M:%S").ok()?;
+    let filled_at = match parts.next() {
+        Some(zone) => zone
+            .parse::<Tz>()
+            .ok()?
+            .from_local_datetime(&naive)
+            .earliest()?
+            .with_timezone(&Utc),
+        None => Local
+            .from_local_datetime(&naive)
+            .earliest()?
+            .with_timezone(&Utc),
+    };
+    Some(filled_at)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageTransition {
+    pub stage: Stage,
+    pub at: DateTime<Utc>,
+}
+
+/// Filter for `Journal::query_trades`. Unset fields match everything;
+/// `from` and `to` bound the time the trade was opened.
+#[derive(Debug, Clone, Default)]
+pub struct TradeFilter {
+    pub symbol: Option<String>,
+    pub account: Option<String>,
+    pub strategy: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Local SQLite store of trades, orders, fills and stage history.
+pub struct Journal {
+    conn: Connection,
+}
+
+impl Journal {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut conn)?;
+        Ok(Journal { conn })
+    }
+
+    /// Closes the database, reporting any error SQLite hits while flushing.
+    pub fn close(self) -> Result<()> {
+        self.conn.close().map_err(|(_, e)| Error::Database(e))
+    }
+
+    pub fn schema_version(&self) -> Result<usize> {
+        let version: i64 = self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        Ok(version as usize)
+    }
+
+    /// Inserts a newly opened trade and stores its journal id on `trade`.
+    pub fn open_trade(&self, trade: &mut Trade) -> Result<i64> {
+        let opened_at = trade.opened_at.unwrap_or_else(Utc::now);
+        self.conn.execute(
+            "INSERT INTO trades (symbol, contract_id, strategy, shares, entry_price, stop_price, stage, opened_at, account)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                trade.symbol,
+                trade.contract_id,
+                trade.strategy,
+                trade.position,
+                trade.entry_price,
+                trade.stop_price,
+                trade.stage.to_string(),
+                opened_at,
+                trade.account,
+            ],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        trade.journal_id = Some(id);
+        self.record_stage(id, trade.stage)?;
+        Ok(id)
+    }
+
+    /// Records the filled entry of a trade journaled while its order worked.
+    pub fn update_entry(&self, trade: &Trade) -> Result<()> {
+        let id = journal_id(trade)?;
+        self.conn.execute(
+            "UPDATE trades SET shares = ?1, entry_price = ?2, stage = ?3, opened_at = ?4 WHERE id = ?5",
+            params![
+                trade.position,
+                trade.entry_price,
+                trade.stage.to_string(),
+                trade.opened_at.unwrap_or_else(Utc::now),
+                id,
+            ],
+        )?;
+        self.record_stage(id, trade.stage)
+    }
+
+    pub fn close_trade(&self, trade: &Trade) -> Result<()> {
+        let id = journal_id(trade)?;
+        self.conn.execute(
+            "UPDATE trades SET exit_price = ?1, realized_pnl = ?2, stage = ?3, closed_at = ?4, excursion = ?5 WHERE id = ?6",
+            params![
+                trade.exit_price,
+                trade.realized_pnl,
+                trade.stage.to_string(),
+                trade.closed_at.unwrap_or_else(Utc::now),
+                serde_json::to_string(&trade.excursion)?,
+                id,
+            ],
+        )?;
+        self.record_stage(id, trade.stage)
+    }
+
+    /// Saves a partial exit: the shares still held and the PnL banked so far.
+    pub fn update_position(&self, trade: &Trade) -> Result<()> {
+        let id = journal_id(trade)?;
+        self.conn.execute(
+            "UPDATE trades SET shares = ?1, realized_pnl = ?2 WHERE id = ?3",
+            params![trade.position, trade.realized_pnl, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_stage(&self, trade: &Trade) -> Result<()> {
+        let id = journal_id(trade)?;
+        self.conn.execute(
+            "UPDATE trades SET stage = ?1 WHERE id = ?2",
+            params![trade.stage.to_string(), id],
+        )?;
+        self.record_stage(id, trade.stage)
+    }
+
+    pub fn update_stop(&self, trade: &Trade) -> Result<()> {
+        let id = journal_id(trade)?;
+        self.conn.execute(
+            "UPDATE trades SET stop_price = ?1 WHERE id = ?2",
+            params![trade.stop_price, id],
+        )?;
+        Ok(())
+    }
+
+    /// Saves the excursions recorded so far, so they survive a restart.
+    pub fn update_excursion(&self, trade: &Trade) -> Result<()> {
+        let id = journal_id(trade)?;
+        self.conn.execute(
+            "UPDATE trades SET excursion = ?1 WHERE id = ?2",
+            params![serde_json::to_string(&trade.excursion)?, id],
+        )?;
+        Ok(())
+    }
+
+    /// Kept out of `TradeRecord` since the sampled price path can be long.
+    pub fn excursion(&self, trade_id: i64) -> Result<Option<Excursion>> {
+        let json: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT excursion FROM trades WHERE id = ?1",
+                params![trade_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+    }
+
+    fn record_stage(&self, trade_id: i64, stage: Stage) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO stage_transitions (trade_id, stage, at) VALUES (?1, ?2, ?3)",
+            params![trade_id, stage.to_string(), Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Records an order as sent. Its account is the FA group for allocated
+    /// orders.
+    pub fn record_order(&self, trade_id: i64, order_id: i32, order: &Order) -> Result<()> {
+        let now = Utc::now();
+        let account = [&order.fa_group, &order.account]
+            .into_iter()
+            .find(|account| !account.is_empty());
+        self.conn.execute(
+            "INSERT INTO orders (order_id, trade_id, action, quantity, order_type, limit_price, aux_price, status, submitted_at, updated_at, account)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'Submitted', ?8, ?8, ?9)",
+            params![
+                order_id,
+                trade_id,
+                order.action.to_string(),
+                order.total_quantity as i64,
+                order.order_type,
+                order.limit_price,
+                order.aux_price,
+                now,
+                account,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_order_status(&self, order_id: i32, status: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE orders SET status = ?1, updated_at = ?2 WHERE order_id = ?3",
+            params![status, Utc::now(), order_id],
+        )?;
+        Ok(())
+    }
+
+    /// Executions can be reported again, e.g. on reconnect; a repeat updates
+    /// the fill but keeps a commission that was already matched to it.
+    pub fn record_fill(&self, trade_id: i64, fill: &Fill) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO fills (execution_id, trade_id, order_id, side, shares, price, commission, filled_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT (execution_id) DO UPDATE SET
+                 trade_id = excluded.trade_id,
+                 order_id = excluded.order_id,
+                 side = excluded.side,
+                 shares = excluded.shares,
+                 price = excluded.price,
+                 commission = COALESCE(fills.commission, excluded.commission),
+                 filled_at = excluded.filled_at",
+            params![
+                fill.execution_id,
+                trade_id,
+                fill.order_id,
+                fill.side,
+                fill.shares,
+                fill.price,
+                fill.commission,
+                fill.filled_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Commission reports arrive after their execution and are matched by id.
+    pub fn record_commission(&self, report: &CommissionReport) -> Result<()> {
+        self.conn.execute(
+            "UPDATE fills SET commission = ?1 WHERE execution_id = ?2",
+            params![report.commission, report.execution_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn trade(&self, id: i64) -> Result<Option<TradeRecord>> {
+        let record = self
+            .conn
+            .query_row(
+                &format!("SELECT {} FROM trades WHERE id = ?1", TRADE_COLUMNS),
+                params![id],
+                trade_from_row,
+            )
+            .optional()?;
+        Ok(record)
+    }
+
+    pub fn query_trades(&self, filter: &TradeFilter) -> Result<Vec<TradeRecord>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {} FROM trades
+             WHERE (?1 IS NULL OR symbol = ?1)
+               AND (?2 IS NULL OR strategy = ?2)
+               AND (?3 IS NULL OR opened_at >= ?3)
+               AND (?4 IS NULL OR opened_at < ?4)
+               AND (?5 IS NULL OR account = ?5)
+             ORDER BY opened_at, id",
+            TRADE_COLUMNS
+        ))?;
+        let rows = statement.query_map(
+            params![
+                filter.symbol,
+                filter.strategy,
+                filter.from,
+                filter.to,
+                filter.account
+            ],
+            trade_from_row,
+        )?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Trades left in `Stage::Open` or `Stage::Hold` by a crash, or in
+    /// `Stage::Disconnect` by a shutdown that left them open.
+    pub fn unfinished_trades(&self) -> Result<Vec<TradeRecord>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {} FROM trades WHERE stage IN ('open', 'hold', 'disconnect') AND closed_at IS NULL
+             ORDER BY opened_at, id",
+            TRADE_COLUMNS
+        ))?;
+        let rows = statement.query_map([], trade_from_row)?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    pub fn trades_by_symbol(&self, symbol: &str) -> Result<Vec<TradeRecord>> {
+        self.query_trades(&TradeFilter {
+            symbol: Some(symbol.to_string()),
+            ..Default::default()
+        })
+    }
+
+    pub fn trades_by_strategy(&self, strategy: &str) -> Result<Vec<TradeRecord>> {
+        self.query_trades(&TradeFilter {
+            strategy: Some(strategy.to_string()),
+            ..Default::default()
+        })
+    }
+
+    pub fn trades_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<TradeRecord>> {
+        self.query_trades(&TradeFilter {
+            from: Some(from),
+            to: Some(to),
+            ..Default::default()
+        })
+    }
+
+    pub fn fills(&self, trade_id: i64) -> Result<Vec<Fill>> {
+        let mut statement = self.conn.prepare(
+            "SELECT execution_id, order_id, side, shares, price, commission, filled_at
+             FROM fills WHERE trade_id = ?1 ORDER BY filled_at",
+        )?;
+        let rows = statement.query_map(params![trade_id], |row| {
+            Ok(Fill {
+                execution_id: row.get(0)?,
+                order_id: row.get(1)?,
+                side: row.get(2)?,
+                shares: row.get(3)?,
+                price: row.get(4)?,
+                commission: row.get(5)?,
+                filled_at: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    pub fn stage_history(&self, trade_id: i64) -> Result<Vec<StageTransition>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT stage, at FROM stage_transitions WHERE trade_id = ?1 ORDER BY id")?;
+        let rows = statement.query_map(params![trade_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, DateTime<Utc>>(1)?))
+        })?;
+        rows.map(|row| {
+            let (stage, at) = row?;
+            Ok(StageTransition {
+                stage: stage.parse()?,
+                at,
+            })
+        })
+        .collect()
+    }
+}
+
+const TRADE_COLUMNS: &str =
+    "id, symbol, contract_id, strategy, shares, entry_price, exit_price, realized_pnl, stage, opened_at, closed_at, stop_price, account";
+
+fn trade_from_row(row: &Row) -> rusqlite::Result<TradeRecord> {
+    let stage: String = row.get(8)?;
+    Ok(TradeRecord {
+        id: row.get(0)?,
+        symbol: row.get(1)?,
+        contract_id: row.get(2)?,
+        account: row.get(12)?,
+        strategy: row.get(3)?,
+        shares: row.get(4)?,
+        entry_price: row.get(5)?,
+        exit_price: row.get(6)?,
+        stop_price: row.get(11)?,
+        realized_pnl: row.get(7)?,
+        stage: stage
+            .parse()
+            .map_err(|e: Error| rusqlite::Error::FromSqlConversionFailure(8, rusqlite::types::Type::Text, e.into()))?,
+        opened_at: row.get(9)?,
+        closed_at: row.get(10)?,
+    })
+}
+
+/// The trade's journal row, which it only has once opened in the journal.
+pub fn journal_id(trade: &Trade) -> Result<i64> {
+    trade
+        .journal_id
+        .ok_or_else(|| Error::Journal(format!("{}: trade has not been journaled", trade.symbol)))
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let applied: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let applied = applied as usize;
+    if applied > MIGRATIONS.len() {
+        return Err(Error::Journal(format!(
+            "database schema version {} is newer than this build supports ({})",
+            applied,
+            MIGRATIONS.len()
+        )));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", (index + 1) as i64)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use crate::error::{Error, Result};
+use crate::trade::Trade;
+use chrono::NaiveDate;
+use ibapi::accounts::PnL;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Realized plus unrealized PnL of every trade in this session.
+pub fn session_pnl(trades: &[Trade]) -> f64 {
+    trades
+        .iter()
+        .map(|trade| trade.realized_pnl + trade.calculate_pnl())
+        .sum()
+}
+
+/// Account-wide daily loss limit. Once tripped, new entries stay locked
+/// until the next session date, across restarts when a state file is set.
+#[derive(Debug, Clone)]
+pub struct KillSwitch {
+    daily_loss_limit: Option<f64>,
+    flatten_on_trip: bool,
+    account_daily_pnl: Option<f64>,
+    tripped_on: Option<NaiveDate>,
+    state_path: Option<PathBuf>,
+}
+
+impl KillSwitch {
+    pub fn new(daily_loss_limit: Option<f64>, flatten_on_trip: bool) -> Self {
+        KillSwitch {
+            daily_loss_limit,
+            flatten_on_trip,
+            account_daily_pnl: None,
+            tripped_on: None,
+            state_path: None,
+        }
+    }
+
+    /// Saves the trip date to `path` from now on, and restores the date an
+    /// earlier run saved there, so restarting on the day the limit tripped
+    /// stays locked.
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        match fs::read_to_string(&path) {
+            Ok(date) => {
+                let date = date.trim().parse().map_err(|e| {
+                    Error::Config(format!(
+                        "Invalid kill switch state in {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+                self.tripped_on = Some(date);
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        self.state_path = Some(path);
+        Ok(self)
+    }
+
+    pub fn flatten_on_trip(&self) -> bool {
+        self.flatten_on_trip
+    }
+
+    pub fn account_daily_pnl(&self) -> Option<f64> {
+        self.account_daily_pnl
+    }
+
+    pub fn update_account_pnl(&mut self, pnl: &PnL) {
+        self.account_daily_pnl = Some(pnl.daily_pnl);
+    }
+
+    /// Returns the losing PnL when this call trips the switch, `None` if it
+    /// stays armed or was already tripped for `today`.
+    pub fn evaluate(&mut self, session_pnl: f64, today: NaiveDate) -> Option<f64> {
+        let limit = self.daily_loss_limit?;
+        if self.is_locked(today) {
+            return None;
+        }
+
+        let worst = match self.account_daily_pnl {
+            Some(account_pnl) => session_pnl.min(account_pnl),
+            None => session_pnl,
+        };
+        if worst <= -limit.abs() {
+            self.tripped_on = Some(today);
+            // The lock holds in memory either way, so a failed save only
+            // loses it across a restart
+            if let Err(e) = self.save() {
+                tracing::warn!("Could not save the kill switch state: {}", e);
+            }
+            Some(worst)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_locked(&self, today: NaiveDate) -> bool {
+        self.tripped_on == Some(today)
+    }
+
+    fn save(&self) -> Result<()> {
+        let (Some(path), Some(date)) = (&self.state_path, self.tripped_on) else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        fs::write(path, date.to_string())?;
+        Ok(())
+    }
+}
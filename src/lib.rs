@@ -1,6 +1,31 @@
+pub mod account;
+pub mod analytics;
+pub mod app;
+pub mod audit;
+pub mod bars;
+pub mod chart;
+pub mod cli;
 pub mod connection;
 pub mod config;
+pub mod depth;
 pub mod error;
+pub mod excursion;
+pub mod export;
+pub mod headless;
+pub mod history;
+pub mod indicators;
+pub mod journal;
+pub mod kill_switch;
+pub mod lots;
+pub mod orders;
+pub mod pnl;
+pub mod quote;
+pub mod recovery;
+pub mod risk;
+pub mod schema;
+pub mod session;
+pub mod sizing;
 pub mod trade;
+pub mod ui;
 
 pub use error::{Error, Result};
\ No newline at end of file
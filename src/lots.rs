@@ -0,0 +1,350 @@
+use crate::error::{Error, Result};
+use crate::journal::{Fill, Journal, TradeFilter};
+use chrono::{DateTime, Duration, Months, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+const WASH_SALE_WINDOW_DAYS: i64 = 30;
+
+/// Which open lots a sale is matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LotMethod {
+    Fifo,
+    Lifo,
+    HighestCost,
+    /// Lots are named explicitly on each sale.
+    SpecificLot,
+}
+
+impl FromStr for LotMethod {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "fifo" => Ok(LotMethod::Fifo),
+            "lifo" => Ok(LotMethod::Lifo),
+            "highest_cost" | "hifo" => Ok(LotMethod::HighestCost),
+            "specific" | "specific_lot" => Ok(LotMethod::SpecificLot),
+            other => Err(Error::Config(format!("Unknown lot method: {}", other))),
+        }
+    }
+}
+
+impl LotMethod {
+    /// Journaled fills carry no lot ids, so a ledger replayed from the
+    /// journal can't match specific lots.
+    pub fn check_replayable(&self) -> Result<()> {
+        match self {
+            LotMethod::SpecificLot => Err(Error::Position(
+                "specific lot matching needs lot ids, which journaled fills don't carry"
+                    .to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Term {
+    ShortTerm,
+    LongTerm,
+}
+
+impl Term {
+    /// Long-term once held for more than one year.
+    pub fn of(acquired_at: DateTime<Utc>, sold_at: DateTime<Utc>) -> Term {
+        match acquired_at.checked_add_months(Months::new(12)) {
+            Some(one_year) if sold_at > one_year => Term::LongTerm,
+            _ => Term::ShortTerm,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lot {
+    pub id: u64,
+    pub symbol: String,
+    pub shares: f64,
+    pub cost_per_share: f64,
+    pub acquired_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClosedLot {
+    pub lot_id: u64,
+    pub symbol: String,
+    pub shares: f64,
+    pub acquired_at: DateTime<Utc>,
+    pub sold_at: DateTime<Utc>,
+    pub cost_basis: f64,
+    pub proceeds: f64,
+    pub term: Term,
+}
+
+impl ClosedLot {
+    pub fn gain(&self) -> f64 {
+        self.proceeds - self.cost_basis
+    }
+}
+
+/// A loss sale with shares of the same symbol bought within 30 days either
+/// side of it. Flagged for review, the ledger does not adjust basis.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WashSaleCandidate {
+    pub symbol: String,
+    pub sold_lot_id: u64,
+    pub sold_at: DateTime<Utc>,
+    pub loss: f64,
+    pub replacement_lot_id: u64,
+    pub replacement_acquired_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct GainSummary {
+    pub short_term: f64,
+    pub long_term: f64,
+}
+
+impl GainSummary {
+    pub fn of(lots: &[ClosedLot]) -> GainSummary {
+        lots.iter()
+            .fold(GainSummary::default(), |mut summary, lot| {
+                match lot.term {
+                    Term::ShortTerm => summary.short_term += lot.gain(),
+                    Term::LongTerm => summary.long_term += lot.gain(),
+                }
+                summary
+            })
+    }
+
+    pub fn total(&self) -> f64 {
+        self.short_term + self.long_term
+    }
+}
+
+/// Per-symbol ledger of open lots and realized lot matches.
+#[derive(Debug, Clone)]
+pub struct LotLedger {
+    method: LotMethod,
+    next_id: u64,
+    open: HashMap<String, Vec<Lot>>,
+    acquisitions: Vec<Lot>,
+    closed: Vec<ClosedLot>,
+}
+
+impl LotLedger {
+    pub fn new(method: LotMethod) -> Self {
+        LotLedger {
+            method,
+            next_id: 1,
+            open: HashMap::new(),
+            acquisitions: Vec::new(),
+            closed: Vec::new(),
+        }
+    }
+
+    /// Replays the fills of the journaled trades matching `filter` in time
+    /// order. Short trades open no lots and are left out.
+    pub fn from_journal(
+        journal: &Journal,
+        filter: &TradeFilter,
+        method: LotMethod,
+    ) -> Result<Self> {
+        method.check_replayable()?;
+        let mut fills = Vec::new();
+        for record in journal.query_trades(filter)? {
+            if record.shares < 0 {
+                continue;
+            }
+            for fill in journal.fills(record.id)? {
+                fills.push((record.symbol.clone(), fill));
+            }
+        }
+        fills.sort_by_key(|(_, fill)| fill.filled_at);
+
+        let mut ledger = LotLedger::new(method);
+        for (symbol, fill) in &fills {
+            ledger.apply_fill(symbol, fill)?;
+        }
+        Ok(ledger)
+    }
+
+    pub fn method(&self) -> LotMethod {
+        self.method
+    }
+
+    pub fn open_lots(&self, symbol: &str) -> &[Lot] {
+        self.open.get(symbol).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn closed_lots(&self) -> &[ClosedLot] {
+        &self.closed
+    }
+
+    /// Buys open a lot, sells are matched with the ledger's method.
+    /// Commissions are folded into cost basis and proceeds. Fills carry no
+    /// lot ids, so a specific-lot ledger refuses their sales.
+    pub fn apply_fill(&mut self, symbol: &str, fill: &Fill) -> Result<()> {
+        let commission = fill.commission.unwrap_or_default();
+        match fill.side.as_str() {
+            "BOT" | "BUY" => {
+                let cost = fill.price + commission / fill.shares;
+                self.buy(symbol, fill.shares, cost, fill.filled_at);
+                Ok(())
+            }
+            "SLD" | "SELL" => {
+                let price = fill.price - commission / fill.shares;
+                self.sell(symbol, fill.shares, price, fill.filled_at, None)
+                    .map(|_| ())
+            }
+            other => Err(Error::Position(format!(
+                "{}: unknown fill side {:?}",
+                symbol, other
+            ))),
+        }
+    }
+
+    pub fn buy(&mut self, symbol: &str, shares: f64, price: f64, at: DateTime<Utc>) -> u64 {
+        let lot = Lot {
+            id: self.next_id,
+            symbol: symbol.to_string(),
+            shares,
+            cost_per_share: price,
+            acquired_at: at,
+        };
+        self.next_id += 1;
+        self.acquisitions.push(lot.clone());
+        self.open.entry(symbol.to_string()).or_default().push(lot);
+        self.next_id - 1
+    }
+
+    /// Matches a sale against open lots. `lot_ids` names the lots to sell,
+    /// in order, and is required for `LotMethod::SpecificLot`. A refused
+    /// sale leaves the ledger unchanged.
+    pub fn sell(
+        &mut self,
+        symbol: &str,
+        shares: f64,
+        price: f64,
+        at: DateTime<Utc>,
+        lot_ids: Option<&[u64]>,
+    ) -> Result<Vec<ClosedLot>> {
+        let lots = self.open_lots(symbol);
+        let held: f64 = lots.iter().map(|lot| lot.shares).sum();
+        if shares > held + f64::EPSILON {
+            return Err(Error::Position(format!(
+                "{}: selling {} shares but only {} held in open lots",
+                symbol, shares, held
+            )));
+        }
+
+        let order: Vec<usize> = match (lot_ids, self.method) {
+            (Some(ids), _) => ids
+                .iter()
+                .map(|id| {
+                    lots.iter().position(|lot| lot.id == *id).ok_or_else(|| {
+                        Error::Position(format!("{}: lot #{} is not open", symbol, id))
+                    })
+                })
+                .collect::<Result<_>>()?,
+            (None, LotMethod::SpecificLot) => {
+                return Err(Error::Position(format!(
+                    "{}: specific lot matching requires lot ids",
+                    symbol
+                )));
+            }
+            (None, LotMethod::Fifo) => (0..lots.len()).collect(),
+            (None, LotMethod::Lifo) => (0..lots.len()).rev().collect(),
+            (None, LotMethod::HighestCost) => {
+                let mut order: Vec<usize> = (0..lots.len()).collect();
+                order.sort_by(|a, b| lots[*b].cost_per_share.total_cmp(&lots[*a].cost_per_share));
+                order
+            }
+        };
+
+        // Work out every match before touching the lots, so a sale the
+        // named lots can't cover is refused as a whole
+        let mut remaining = shares;
+        let mut matched = Vec::new();
+        for index in order {
+            if remaining <= f64::EPSILON {
+                break;
+            }
+            let lot = &lots[index];
+            // A lot named twice only has what the first match left
+            let available = lot.shares
+                - matched
+                    .iter()
+                    .filter(|(matched, _)| *matched == index)
+                    .map(|(_, closed): &(usize, ClosedLot)| closed.shares)
+                    .sum::<f64>();
+            let taken = remaining.min(available);
+            if taken <= f64::EPSILON {
+                continue;
+            }
+            remaining -= taken;
+            matched.push((
+                index,
+                ClosedLot {
+                    lot_id: lot.id,
+                    symbol: symbol.to_string(),
+                    shares: taken,
+                    acquired_at: lot.acquired_at,
+                    sold_at: at,
+                    cost_basis: taken * lot.cost_per_share,
+                    proceeds: taken * price,
+                    term: Term::of(lot.acquired_at, at),
+                },
+            ));
+        }
+        if remaining > f64::EPSILON {
+            return Err(Error::Position(format!(
+                "{}: named lots cover only {} of {} shares",
+                symbol,
+                shares - remaining,
+                shares
+            )));
+        }
+
+        let lots = self.open.entry(symbol.to_string()).or_default();
+        for (index, closed) in &matched {
+            lots[*index].shares -= closed.shares;
+        }
+        lots.retain(|lot| lot.shares > f64::EPSILON);
+
+        let matched: Vec<ClosedLot> = matched.into_iter().map(|(_, closed)| closed).collect();
+        self.closed.extend(matched.iter().cloned());
+        Ok(matched)
+    }
+
+    pub fn realized_gains(&self) -> GainSummary {
+        GainSummary::of(&self.closed)
+    }
+
+    pub fn wash_sale_candidates(&self) -> Vec<WashSaleCandidate> {
+        let window = Duration::days(WASH_SALE_WINDOW_DAYS);
+        let mut candidates = Vec::new();
+        for sale in self.closed.iter().filter(|lot| lot.gain() < 0.0) {
+            let replacement = self.acquisitions.iter().find(|lot| {
+                lot.symbol == sale.symbol
+                    && lot.id != sale.lot_id
+                    && lot.acquired_at >= sale.sold_at - window
+                    && lot.acquired_at <= sale.sold_at + window
+            });
+            if let Some(replacement) = replacement {
+                candidates.push(WashSaleCandidate {
+                    symbol: sale.symbol.clone(),
+                    sold_lot_id: sale.lot_id,
+                    sold_at: sale.sold_at,
+                    loss: sale.gain(),
+                    replacement_lot_id: replacement.id,
+                    replacement_acquired_at: replacement.acquired_at,
+                });
+            }
+        }
+        candidates
+    }
+}
@@ -1,10 +1,707 @@
-use ibapi::prelude::*;
+use chrono::{Days, NaiveTime, Utc};
+use clap::{CommandFactory, Parser};
+use clap_complete::Shell;
+use ibapi::orders::Action;
+use ibxrust::account::{self, Account, PnlCheck};
+use ibxrust::analytics::PerformanceReport;
+use ibxrust::app::App;
+use ibxrust::cli::{
+    BarsArgs, Cli, Command, ExportArgs, HistoryArgs, OrderArgs, QuoteArgs, ReportArgs, TradeArgs,
+};
+use ibxrust::config::{self, Config};
+use ibxrust::connection::Connection;
+use ibxrust::excursion::Excursion;
+use ibxrust::export::{self, ExportColumn, ExportFormat, TaxLots};
+use ibxrust::headless::{self, OrderRequest, Policy};
+use ibxrust::history::{self, BarCache, BarQuery, Pacer};
+use ibxrust::journal::{Journal, TradeFilter};
+use ibxrust::kill_switch;
+use ibxrust::lots::{GainSummary, WashSaleCandidate};
+use ibxrust::quote::{self, QuoteRow};
+use ibxrust::recovery::{self, Recovery};
+use ibxrust::schema;
+use ibxrust::trade::{Stage, Trade};
+use ibxrust::ui::{self, format_money};
+use serde::Serialize;
+use serde_json::json;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Serialize)]
+struct Startup {
+    account: String,
+    accounts: Vec<String>,
+    recovery: Recovery,
+    trades: Vec<Trade>,
+}
+
+#[derive(Serialize)]
+struct Accounts {
+    default: String,
+    accounts: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct AccountView {
+    account: Account,
+    pnl_checks: Vec<PnlCheck>,
+}
+
+#[derive(Serialize)]
+struct PositionRow {
+    account: String,
+    symbol: String,
+    contract_id: i32,
+    position: f64,
+    average_cost: f64,
+}
+
+#[derive(Serialize)]
+struct OrderRow {
+    order_id: i32,
+    symbol: String,
+    action: String,
+    quantity: f64,
+    order_type: String,
+    limit_price: Option<f64>,
+    aux_price: Option<f64>,
+    status: String,
+}
+
+#[derive(Serialize)]
+struct ExportResult {
+    format: &'static str,
+    trades: usize,
+    output: Option<PathBuf>,
+    content: Option<String>,
+}
+
+/// The performance report, with realized gains split by tax term.
+#[derive(Serialize)]
+struct ReportResult<'a> {
+    #[serde(flatten)]
+    report: &'a PerformanceReport,
+    gains: GainSummary,
+    wash_sales: &'a [WashSaleCandidate],
+}
 
 #[tokio::main]
 async fn main() {
-    let connection_url = "127.0.0.1:7500";
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        Some(Command::Completions(args)) => {
+            completions(args.shell);
+            Ok(())
+        }
+        command => Config::load(&cli.overrides()).and_then(|config| match command {
+            Some(Command::Trade(args)) => run(&cli, &config, args),
+            Some(Command::Buy(args)) => order(&cli, &config, Action::Buy, args),
+            Some(Command::Sell(args)) => order(&cli, &config, Action::Sell, args),
+            Some(Command::Quote(args)) => quotes(&cli, &config, args),
+            Some(Command::Bars(args)) => bars(&cli, &config, args),
+            Some(Command::Accounts) => accounts(&cli, &config),
+            Some(Command::Account) => show_account(&cli, &config),
+            Some(Command::Positions) => positions(&cli, &config),
+            Some(Command::Orders) => orders(&cli, &config),
+            Some(Command::History(args)) => history(&cli, &config, args),
+            Some(Command::Report(args)) => report(&cli, &config, args),
+            Some(Command::Export(args)) => export(&cli, &config, args),
+            Some(Command::Config) => show_config(&cli, &config),
+            Some(Command::Completions(_)) | None => run(&cli, &config, &cli.trade),
+        }),
+    };
+    if let Err(e) = result {
+        if cli.json() {
+            let error = json!({ "error": e.to_string(), "exit_code": e.exit_code() });
+            if let Ok(document) = schema::to_json(&error) {
+                println!("{}", document);
+            }
+        }
+        eprintln!("Error: {}", e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run(cli: &Cli, config: &Config, args: &TradeArgs) -> ibxrust::Result<()> {
+    let connection = Connection::connect(config)?;
+    let journal = Journal::open(&config.journal_path)?;
+    let recovery = recovery::recover(connection.client(), &journal)?;
+
+    let mut symbols: Vec<String> = args
+        .symbols
+        .iter()
+        .map(|symbol| symbol.to_uppercase())
+        .chain(config.watchlist.iter().cloned())
+        .collect();
+    if symbols.is_empty() && recovery.trades.is_empty() && !cli.json() {
+        symbols = prompt_symbols()?;
+    }
+
+    // Recovered trades are live, so they are always on the watchlist
+    let mut trades = recovery.trades.clone();
+    for symbol in symbols {
+        if trades.iter().any(|trade| trade.symbol == symbol) {
+            continue;
+        }
+        let mut trade = Trade::new(symbol);
+        trade.account = Some(connection.account().to_string());
+        trade.excursion = Excursion::new(config.price_sample_secs);
+        trade.create_contract();
+        trades.push(trade);
+    }
+    for trade in trades.iter_mut().filter(|trade| trade.contract.is_some()) {
+        trade.resolve_contract(connection.client())?;
+    }
+
+    if cli.json() {
+        let startup = Startup {
+            account: connection.account().to_string(),
+            accounts: connection.accounts().to_vec(),
+            recovery,
+            trades,
+        };
+        println!("{}", schema::to_json(&startup)?);
+        return Ok(());
+    }
+
+    let mut app = App::new(
+        connection.client(),
+        config,
+        &journal,
+        connection.account(),
+        trades,
+    )?;
+    let dashboard = app.dashboard();
+    dashboard.message(format!(
+        "Connected to TWS at {}. Up/down select, y/b buy, s sell, a account, q quit",
+        connection.url()
+    ));
+    dashboard.message(format!(
+        "Trading account {} (managed: {})",
+        connection.account(),
+        connection.accounts().join(", ")
+    ));
+    for trade in &recovery.trades {
+        dashboard.message(format!(
+            "Recovered {} {} shares @ ${:.2} ({})",
+            trade.symbol, trade.position, trade.entry_price, trade.stage
+        ));
+    }
+    for issue in &recovery.issues {
+        dashboard.message(format!("Warning: {}", issue));
+    }
+
+    let trades = app.run()?;
+    journal.close()?;
+    connection.disconnect();
+    println!(
+        "*** Final PnL: {}",
+        format_money(kill_switch::session_pnl(&trades))
+    );
+    for trade in trades.iter().filter(|trade| trade.position != 0) {
+        println!(
+            "{} position of {} shares left open",
+            trade.symbol, trade.position
+        );
+    }
+    Ok(())
+}
+
+fn prompt_symbols() -> ibxrust::Result<Vec<String>> {
+    print!("Enter ticker symbols: ");
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    let symbols = config::parse_symbols(&line);
+    if symbols.is_empty() {
+        return Err(ibxrust::Error::Other(
+            "No ticker symbol entered".to_string(),
+        ));
+    }
+    Ok(symbols)
+}
+
+fn order(cli: &Cli, config: &Config, action: Action, args: &OrderArgs) -> ibxrust::Result<()> {
+    let policy = Policy::load(&config.policy_path)?;
+    let request = OrderRequest {
+        symbol: args.symbol.to_uppercase(),
+        action,
+        quantity: args.qty,
+        limit_price: args.limit,
+        wait_fill: args.wait_fill.map(Duration::from_secs),
+    };
+    let connection = Connection::connect(config)?;
+    let journal = Journal::open(&config.journal_path)?;
+    let result = headless::place(
+        connection.client(),
+        config,
+        &journal,
+        connection.account(),
+        &policy,
+        &request,
+        args.yes,
+    );
+    journal.close()?;
+    connection.disconnect();
+    let outcome = result?;
+
+    if cli.json() {
+        println!("{}", schema::to_json(&outcome)?);
+        return Ok(());
+    }
+    println!(
+        "{} {} {} shares, order #{} {}",
+        outcome.action, outcome.symbol, outcome.quantity, outcome.order_id, outcome.status
+    );
+    if let Some(price) = outcome.average_fill_price {
+        println!("Filled {} @ ${:.2}", outcome.filled, price);
+    }
+    Ok(())
+}
+
+fn quotes(cli: &Cli, config: &Config, args: &QuoteArgs) -> ibxrust::Result<()> {
+    let connection = Connection::connect(config)?;
+    let symbols: Vec<String> = args
+        .symbols
+        .iter()
+        .flat_map(|symbols| config::parse_symbols(symbols))
+        .collect();
+    let rows = quote::fetch_all(connection.client(), &symbols);
+    connection.disconnect();
+
+    if cli.json() {
+        println!("{}", schema::to_json(&rows)?);
+        return Ok(());
+    }
+    println!(
+        "{:<8} {:>10} {:>10} {:>10} {:>12} {:>10} {:>8}  {:<13}",
+        "Symbol", "Bid", "Ask", "Last", "Volume", "Change", "Chg %", "Session"
+    );
+    let price = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.2}", v));
+    for row in &rows {
+        match row {
+            QuoteRow::Quote(quote) => println!(
+                "{:<8} {:>10} {:>10} {:>10} {:>12} {:>10} {:>8}  {:<13}",
+                quote.symbol,
+                price(quote.bid),
+                price(quote.ask),
+                price(quote.last),
+                quote
+                    .volume
+                    .map_or("-".to_string(), |v| format!("{:.0}", v)),
+                quote
+                    .change()
+                    .map_or("-".to_string(), |v| format!("{:+.2}", v)),
+                quote
+                    .change_percent()
+                    .map_or("-".to_string(), |v| format!("{:+.2}%", v)),
+                quote
+                    .session
+                    .map_or("-".to_string(), |session| session.to_string())
+            ),
+            QuoteRow::Failed { symbol, error } => println!("{:<8} {}", symbol, error),
+        }
+    }
+    Ok(())
+}
+
+/// Loads bars for the symbol's whole days from `--from` to `--to` (UTC),
+/// requesting from TWS only what the cache lacks.
+fn bars(cli: &Cli, config: &Config, args: &BarsArgs) -> ibxrust::Result<()> {
+    let to = args.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = args
+        .from
+        .unwrap_or_else(|| to - Days::new(u64::from(args.days)));
+    let start = from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let end = (to + Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+    let query = BarQuery {
+        bar_size: args.size,
+        bar_type: args.bar_type,
+        use_rth: !args.outside_rth,
+    };
+
+    let mut cache = BarCache::open(&config.bar_cache_path)?;
+    let connection = Connection::connect(config)?;
+    let mut trade = Trade::new(args.symbol.to_uppercase());
+    trade.create_contract();
+    let bars = trade.resolve_contract(connection.client()).and_then(|()| {
+        let contract = trade.contract.as_ref().ok_or_else(|| {
+            ibxrust::Error::MarketData(format!("{}: contract not created", trade.symbol))
+        })?;
+        history::fetch(
+            connection.client(),
+            &mut cache,
+            &mut Pacer::new(),
+            contract,
+            &query,
+            start,
+            end,
+        )
+    });
+    connection.disconnect();
+    let bars = bars?;
+
+    if cli.json() {
+        println!("{}", schema::to_json(&bars)?);
+        return Ok(());
+    }
+    let writer: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    history::write_csv(writer, &bars)
+}
+
+fn accounts(cli: &Cli, config: &Config) -> ibxrust::Result<()> {
+    let connection = Connection::connect(config)?;
+    let accounts = Accounts {
+        default: connection.account().to_string(),
+        accounts: connection.accounts().to_vec(),
+    };
+    connection.disconnect();
+
+    if cli.json() {
+        println!("{}", schema::to_json(&accounts)?);
+        return Ok(());
+    }
+    for account in &accounts.accounts {
+        let marker = if *account == accounts.default {
+            "*"
+        } else {
+            " "
+        };
+        println!("{} {}", marker, account);
+    }
+    Ok(())
+}
+
+fn show_account(cli: &Cli, config: &Config) -> ibxrust::Result<()> {
+    let connection = Connection::connect(config)?;
+    let account = Account::fetch(connection.client(), connection.account())?;
+    connection.disconnect();
+
+    let journal = Journal::open(&config.journal_path)?;
+    let held: Vec<Trade> = journal
+        .unfinished_trades()?
+        .into_iter()
+        .filter(|record| record.stage == Stage::Hold)
+        .map(|record| {
+            let mut trade = Trade::new(record.symbol);
+            trade.contract_id = record.contract_id;
+            trade.account = record.account;
+            trade.position = record.shares;
+            trade.entry_price = record.entry_price;
+            trade
+        })
+        .collect();
+    let pnl_checks = account::reconcile(&account, &held);
+
+    if cli.json() {
+        let view = AccountView {
+            account,
+            pnl_checks,
+        };
+        println!("{}", schema::to_json(&view)?);
+        return Ok(());
+    }
+    for line in ui::account_lines(&account, &pnl_checks, config.pnl_tolerance) {
+        println!("{}", line.text);
+    }
+    Ok(())
+}
+
+fn positions(cli: &Cli, config: &Config) -> ibxrust::Result<()> {
+    let connection = Connection::connect(config)?;
+    let rows: Vec<PositionRow> = recovery::fetch_positions(connection.client())?
+        .into_iter()
+        .filter(|position| position.position != 0.0)
+        .filter(|position| {
+            config
+                .account
+                .as_ref()
+                .is_none_or(|account| *account == position.account)
+        })
+        .map(|position| PositionRow {
+            account: position.account,
+            symbol: position.contract.symbol,
+            contract_id: position.contract.contract_id,
+            position: position.position,
+            average_cost: position.average_cost,
+        })
+        .collect();
+    connection.disconnect();
+
+    if cli.json() {
+        println!("{}", schema::to_json(&rows)?);
+        return Ok(());
+    }
+    if rows.is_empty() {
+        println!("No open positions");
+        return Ok(());
+    }
+    println!(
+        "{:<12} {:<8} {:>10} {:>12}",
+        "Account", "Symbol", "Position", "Avg cost"
+    );
+    for row in &rows {
+        println!(
+            "{:<12} {:<8} {:>10} {:>12.2}",
+            row.account, row.symbol, row.position, row.average_cost
+        );
+    }
+    Ok(())
+}
+
+fn orders(cli: &Cli, config: &Config) -> ibxrust::Result<()> {
+    let connection = Connection::connect(config)?;
+    let rows: Vec<OrderRow> = recovery::fetch_open_orders(connection.client())?
+        .into_iter()
+        .filter(|data| {
+            config
+                .account
+                .as_ref()
+                .is_none_or(|account| *account == data.order.account)
+        })
+        .map(|data| OrderRow {
+            order_id: data.order_id,
+            symbol: data.contract.symbol,
+            action: data.order.action.to_string(),
+            quantity: data.order.total_quantity,
+            order_type: data.order.order_type,
+            limit_price: data.order.limit_price,
+            aux_price: data.order.aux_price,
+            status: data.order_state.status,
+        })
+        .collect();
+    connection.disconnect();
+
+    if cli.json() {
+        println!("{}", schema::to_json(&rows)?);
+        return Ok(());
+    }
+    if rows.is_empty() {
+        println!("No working orders");
+        return Ok(());
+    }
+    println!(
+        "{:>8} {:<8} {:<5} {:>8} {:<6} {:>10} {:>10} {:<12}",
+        "Order", "Symbol", "Side", "Qty", "Type", "Limit", "Aux", "Status"
+    );
+    let price = |value: Option<f64>| value.map_or(String::new(), |v| format!("{:.2}", v));
+    for row in &rows {
+        println!(
+            "{:>8} {:<8} {:<5} {:>8} {:<6} {:>10} {:>10} {:<12}",
+            row.order_id,
+            row.symbol,
+            row.action,
+            row.quantity,
+            row.order_type,
+            price(row.limit_price),
+            price(row.aux_price),
+            row.status
+        );
+    }
+    Ok(())
+}
+
+fn history(cli: &Cli, config: &Config, args: &HistoryArgs) -> ibxrust::Result<()> {
+    let journal = Journal::open(&config.journal_path)?;
+    let filter = TradeFilter {
+        symbol: args.symbol.as_ref().map(|symbol| symbol.to_uppercase()),
+        account: config.account.clone(),
+        strategy: args.strategy.clone(),
+        from: args
+            .from
+            .map(|date| date.and_time(NaiveTime::MIN).and_utc()),
+        to: args
+            .to
+            .and_then(|date| date.succ_opt())
+            .map(|date| date.and_time(NaiveTime::MIN).and_utc()),
+    };
+    let trades = journal.query_trades(&filter)?;
+
+    if cli.json() {
+        println!("{}", schema::to_json(&trades)?);
+        return Ok(());
+    }
+    if trades.is_empty() {
+        println!("No trades in the journal");
+        return Ok(());
+    }
+    println!(
+        "{:>5} {:<17} {:<8} {:>7} {:>10} {:>10} {:>12} {:<10}",
+        "Id", "Opened", "Symbol", "Shares", "Entry", "Exit", "PnL", "Stage"
+    );
+    for trade in &trades {
+        println!(
+            "{:>5} {:<17} {:<8} {:>7} {:>10.2} {:>10} {:>12} {:<10}",
+            trade.id,
+            trade.opened_at.format("%Y-%m-%d %H:%M"),
+            trade.symbol,
+            trade.shares,
+            trade.entry_price,
+            trade
+                .exit_price
+                .map_or(String::new(), |price| format!("{:.2}", price)),
+            trade
+                .realized_pnl
+                .map_or(String::new(), |pnl| format!("{:.2}", pnl)),
+            trade.stage.to_string()
+        );
+    }
+    Ok(())
+}
+
+fn show_config(cli: &Cli, config: &Config) -> ibxrust::Result<()> {
+    if cli.json() {
+        println!("{}", schema::to_json(config)?);
+        return Ok(());
+    }
+    let value = serde_json::to_value(config)?;
+    if let Some(settings) = value.as_object() {
+        for (key, value) in settings {
+            println!("{:<24} {}", key, value);
+        }
+    }
+    Ok(())
+}
+
+fn completions(shell: Shell) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+}
+
+fn export(cli: &Cli, config: &Config, args: &ExportArgs) -> ibxrust::Result<()> {
+    let journal = Journal::open(&config.journal_path)?;
+    let filter = TradeFilter {
+        symbol: args.symbol.as_ref().map(|symbol| symbol.to_uppercase()),
+        account: config.account.clone(),
+        strategy: args.strategy.clone(),
+        ..Default::default()
+    };
+    let trades = export::completed_trades(&journal, &filter, args.from, args.to)?;
+    let tax_lots = match args.format {
+        ExportFormat::TaxLots => {
+            export::tax_lots(&journal, &filter, config.lot_method, args.from, args.to)?
+        }
+        ExportFormat::Csv => TaxLots::default(),
+    };
+
+    let write = |writer: &mut dyn Write| match args.format {
+        ExportFormat::Csv if args.columns.is_empty() => {
+            export::write_csv(writer, &trades, ExportColumn::ALL)
+        }
+        ExportFormat::Csv => export::write_csv(writer, &trades, &args.columns),
+        ExportFormat::TaxLots => export::write_tax_lots(writer, &trades, &tax_lots),
+    };
+    if !cli.json() {
+        return match &args.output {
+            Some(path) => write(&mut File::create(path)?),
+            None => write(&mut io::stdout().lock()),
+        };
+    }
+
+    // With --json the export is described in the envelope, and carried in
+    // it when there is no file to write
+    let mut content = Vec::new();
+    write(&mut content)?;
+    let content = String::from_utf8_lossy(&content).into_owned();
+    let content = match &args.output {
+        Some(path) => {
+            std::fs::write(path, content)?;
+            None
+        }
+        None => Some(content),
+    };
+    let result = ExportResult {
+        format: match args.format {
+            ExportFormat::Csv => "csv",
+            ExportFormat::TaxLots => "tax-lots",
+        },
+        trades: trades.len(),
+        output: args.output.clone(),
+        content,
+    };
+    println!("{}", schema::to_json(&result)?);
+    Ok(())
+}
+
+fn report(cli: &Cli, config: &Config, args: &ReportArgs) -> ibxrust::Result<()> {
+    let journal = Journal::open(&config.journal_path)?;
+    let filter = TradeFilter {
+        symbol: args.symbol.as_ref().map(|symbol| symbol.to_uppercase()),
+        account: config.account.clone(),
+        strategy: args.strategy.clone(),
+        ..Default::default()
+    };
+    let (from, to) = if args.session {
+        let today = Utc::now().date_naive();
+        (Some(today), Some(today))
+    } else {
+        (args.from, args.to)
+    };
+    let trades = export::completed_trades(&journal, &filter, from, to)?;
+    let report = PerformanceReport::compute(&trades);
+    let tax_lots = export::tax_lots(&journal, &filter, config.lot_method, from, to)?;
+    let gains = tax_lots.gains();
+
+    if cli.json() {
+        let result = ReportResult {
+            report: &report,
+            gains,
+            wash_sales: &tax_lots.wash_sales,
+        };
+        println!("{}", schema::to_json(&result)?);
+        return Ok(());
+    }
+
+    let stats = &report.stats;
+    let ratio = |value: Option<f64>| value.map_or("n/a".to_string(), |v| format!("{:.2}", v));
+    println!("Trades:          {}", stats.trades);
+    println!(
+        "Win rate:        {:.1}% ({} won, {} lost)",
+        stats.win_rate * 100.0,
+        stats.wins,
+        stats.losses
+    );
+    println!("Average win:     ${:.2}", stats.average_win);
+    println!("Average loss:    ${:.2}", stats.average_loss);
+    println!("Profit factor:   {}", ratio(stats.profit_factor));
+    println!("Expectancy:      ${:.2}", stats.expectancy);
+    println!("Total PnL:       ${:.2}", stats.total_pnl);
+    println!("Max drawdown:    ${:.2}", report.max_drawdown);
+    println!("Sharpe:          {}", ratio(report.sharpe));
+    println!("Sortino:         {}", ratio(report.sortino));
+    println!(
+        "Average hold:    {:.1} min",
+        stats.average_hold_seconds / 60.0
+    );
+    println!("Short-term gain: ${:.2}", gains.short_term);
+    println!("Long-term gain:  ${:.2}", gains.long_term);
+    println!("Wash sales:      {}", tax_lots.wash_sales.len());
 
-    let client = Client::connect(connection_url, 100)
-        .expect("connection to TWS failed!");
-    println!("Successfully connected to TWS at {connection_url}");
+    if !report.by_symbol.is_empty() {
+        println!();
+        println!(
+            "{:<8} {:>6} {:>8} {:>12} {:>12}",
+            "Symbol", "Trades", "Win %", "Expectancy", "PnL"
+        );
+        for symbol in &report.by_symbol {
+            println!(
+                "{:<8} {:>6} {:>7.1}% {:>12.2} {:>12.2}",
+                symbol.symbol,
+                symbol.stats.trades,
+                symbol.stats.win_rate * 100.0,
+                symbol.stats.expectancy,
+                symbol.stats.total_pnl
+            );
+        }
+    }
+    Ok(())
 }
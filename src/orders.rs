@@ -0,0 +1,214 @@
+use crate::account::FaMethod;
+use crate::audit::AuditLog;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::kill_switch::{self, KillSwitch};
+use crate::risk::{OrderCheck, RiskEngine};
+use crate::session::OutsideRthPolicy;
+use crate::trade::Trade;
+use ibapi::client::Subscription;
+use ibapi::orders::{order_builder, Action, Order, PlaceOrder};
+use ibapi::Client;
+use serde_json::json;
+
+/// Single path for sending orders to TWS. Every order passes the risk
+/// engine first, and entries are refused while the kill switch is tripped.
+pub struct OrderManager<'a> {
+    client: &'a Client,
+    risk: RiskEngine,
+    kill_switch: KillSwitch,
+    outside_rth: bool,
+    outside_rth_policy: OutsideRthPolicy,
+    fa_group: Option<String>,
+    fa_method: Option<FaMethod>,
+}
+
+impl<'a> OrderManager<'a> {
+    pub fn new(client: &'a Client, config: &Config) -> Result<Self> {
+        Ok(OrderManager {
+            client,
+            risk: RiskEngine::new(config.risk_limits.clone()),
+            kill_switch: KillSwitch::new(config.daily_loss_limit, config.flatten_on_loss_limit)
+                .with_state_file(&config.kill_switch_state_path)?,
+            outside_rth: config.outside_rth,
+            outside_rth_policy: config.outside_rth_policy,
+            fa_group: config.fa_group.clone(),
+            fa_method: config.fa_method,
+        })
+    }
+
+    /// Sends the order to the configured FA group, else to the trade's
+    /// account.
+    fn route(&self, order: &mut Order, trade: &Trade) {
+        match &self.fa_group {
+            Some(group) => {
+                order.fa_group = group.clone();
+                if let Some(method) = self.fa_method {
+                    order.fa_method = method.to_string();
+                }
+            }
+            None => order.account = trade.account.clone().unwrap_or_default(),
+        }
+    }
+
+    pub fn kill_switch(&mut self) -> &mut KillSwitch {
+        &mut self.kill_switch
+    }
+
+    pub fn submit(
+        &mut self,
+        trade: &Trade,
+        action: Action,
+        shares: i32,
+        limit_price: Option<f64>,
+    ) -> Result<(i32, Order, Subscription<'a, PlaceOrder>)> {
+        let contract = trade
+            .contract
+            .as_ref()
+            .ok_or_else(|| Error::Order(format!("{}: contract not created", trade.symbol)))?;
+
+        let signed_shares = if action == Action::Buy { shares } else { -shares };
+        let is_entry = (trade.position + signed_shares).abs() > trade.position.abs();
+        if is_entry && self.kill_switch.is_locked(chrono::Local::now().date_naive()) {
+            return Err(Error::Risk(
+                "daily loss limit reached, new entries are locked until the next session".to_string(),
+            ));
+        }
+
+        self.risk.check(&OrderCheck {
+            symbol: &trade.symbol,
+            action,
+            shares,
+            limit_price,
+            last_price: trade.current_price,
+            current_position: trade.position,
+        })?;
+
+        let outside_session = trade.market_session().filter(|session| !session.is_regular());
+        if limit_price.is_none() {
+            self.outside_rth_policy
+                .check_market_order(&trade.symbol, trade.market_session())?;
+        }
+
+        let mut order = match limit_price {
+            Some(price) => order_builder::limit_order(action, shares as f64, price),
+            None => order_builder::market_order(action, shares as f64),
+        };
+        order.outside_rth = self.outside_rth && outside_session.is_some();
+        self.route(&mut order, trade);
+        let order_id = self.client.next_order_id();
+        let subscription = self.client.place_order(order_id, contract, &order)?;
+        self.risk.record_sent();
+        Ok((order_id, order, subscription))
+    }
+
+    /// Places a protective stop for the trade's whole position and records
+    /// it on the trade. Like exits, stops only reduce exposure.
+    pub fn attach_stop(
+        &mut self,
+        trade: &mut Trade,
+        stop_price: f64,
+    ) -> Result<(i32, Order, Subscription<'a, PlaceOrder>)> {
+        let mut order = stop_order(trade, stop_price)
+            .ok_or_else(|| Error::Order(format!("{}: no position to protect", trade.symbol)))?;
+        let contract = trade
+            .contract
+            .as_ref()
+            .ok_or_else(|| Error::Order(format!("{}: contract not created", trade.symbol)))?;
+
+        self.route(&mut order, trade);
+        let order_id = self.client.next_order_id();
+        let subscription = self.client.place_order(order_id, contract, &order)?;
+        trade.stop_price = Some(stop_price);
+        trade.stop_order_id = Some(order_id);
+        Ok((order_id, order, subscription))
+    }
+
+    /// Sends a market order closing the trade's whole position. Exits only
+    /// reduce exposure, so they skip the per-order entry limits.
+    pub fn flatten(&mut self, trade: &Trade) -> Result<Option<(i32, Subscription<'a, PlaceOrder>)>> {
+        if trade.position == 0 {
+            return Ok(None);
+        }
+        let contract = trade
+            .contract
+            .as_ref()
+            .ok_or_else(|| Error::Order(format!("{}: contract not created", trade.symbol)))?;
+
+        let action = if trade.position > 0 {
+            Action::Sell
+        } else {
+            Action::Buy
+        };
+        let mut order = order_builder::market_order(action, trade.position.abs() as f64);
+        self.route(&mut order, trade);
+        let order_id = self.client.next_order_id();
+        let subscription = self.client.place_order(order_id, contract, &order)?;
+        Ok(Some((order_id, subscription)))
+    }
+
+    /// Checks the daily loss limit and, when it trips, cancels the
+    /// `working` orders one by one, optionally flattens all positions and
+    /// records the event. Returns the flattening orders that were sent,
+    /// each with the index of the trade it closes.
+    pub fn enforce_daily_loss(
+        &mut self,
+        trades: &[Trade],
+        working: &[i32],
+        audit: &mut AuditLog,
+    ) -> Result<Vec<(usize, i32, Subscription<'a, PlaceOrder>)>> {
+        let session_pnl = kill_switch::session_pnl(trades);
+        let Some(loss) = self.kill_switch.evaluate(session_pnl, chrono::Local::now().date_naive()) else {
+            return Ok(Vec::new());
+        };
+
+        let mut exits = Vec::new();
+        let mut failures = Vec::new();
+        for order_id in working {
+            if let Err(e) = self.client.cancel_order(*order_id, "") {
+                failures.push(format!("cancelling #{}: {}", order_id, e));
+            }
+        }
+        if self.kill_switch.flatten_on_trip() {
+            for (index, trade) in trades.iter().enumerate() {
+                match self.flatten(trade) {
+                    Ok(Some((order_id, subscription))) => {
+                        exits.push((index, order_id, subscription))
+                    }
+                    Ok(None) => {}
+                    Err(e) => failures.push(format!("{}: {}", trade.symbol, e)),
+                }
+            }
+        }
+
+        audit.record(
+            "kill_switch",
+            json!({
+                "loss": loss,
+                "session_pnl": session_pnl,
+                "account_daily_pnl": self.kill_switch.account_daily_pnl(),
+                "cancelled_orders": working,
+                "flatten": self.kill_switch.flatten_on_trip(),
+                "flatten_orders": exits.iter().map(|(_, order_id, _)| *order_id).collect::<Vec<_>>(),
+                "flatten_failures": failures,
+            }),
+        )?;
+
+        Ok(exits)
+    }
+}
+
+/// The stop order protecting a trade's whole position: a sell stop for a
+/// long and a buy stop for a short. `None` while the trade holds nothing.
+pub fn stop_order(trade: &Trade, stop_price: f64) -> Option<Order> {
+    let action = match trade.position {
+        0 => return None,
+        position if position > 0 => Action::Sell,
+        _ => Action::Buy,
+    };
+    Some(order_builder::stop(
+        action,
+        trade.position.abs() as f64,
+        stop_price,
+    ))
+}
@@ -0,0 +1,268 @@
+use crate::error::Result;
+use crate::journal::Fill;
+use crate::kill_switch;
+use crate::trade::{Stage, Trade};
+use crate::ui::format_money;
+use ibapi::accounts::{PnL, PnLSingle};
+use ibapi::client::Subscription;
+use ibapi::Client;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Daily, unrealized and realized PnL, from IB or computed locally.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PnlBreakdown {
+    pub daily: f64,
+    pub unrealized: f64,
+    pub realized: f64,
+}
+
+impl PnlBreakdown {
+    /// IB leaves unrealized and realized out until there is something to
+    /// report.
+    pub fn from_account(pnl: &PnL) -> Self {
+        PnlBreakdown {
+            daily: pnl.daily_pnl,
+            unrealized: pnl.unrealized_pnl.unwrap_or_default(),
+            realized: pnl.realized_pnl.unwrap_or_default(),
+        }
+    }
+
+    pub fn from_position(pnl: &PnLSingle) -> Self {
+        PnlBreakdown {
+            daily: pnl.daily_pnl,
+            unrealized: pnl.unrealized_pnl,
+            realized: pnl.realized_pnl,
+        }
+    }
+
+    /// Our own figures over the session's trades, net of the commissions
+    /// journaled for them (keyed by journal id) as IB's are. Daily is
+    /// realized plus unrealized, so it only matches IB's when no position
+    /// was carried in from a previous day.
+    pub fn local(trades: &[Trade], commissions: &HashMap<i64, Commissions>) -> Self {
+        let commission = |trade: &Trade| Commissions::find(trade, commissions);
+        let unrealized: f64 = trades
+            .iter()
+            .map(|trade| trade.calculate_pnl() - commission(trade).unrealized)
+            .sum();
+        let realized: f64 = trades
+            .iter()
+            .map(|trade| trade.realized_pnl - commission(trade).realized)
+            .sum();
+        PnlBreakdown {
+            daily: kill_switch::session_pnl(trades)
+                - trades
+                    .iter()
+                    .map(|trade| commission(trade).total())
+                    .sum::<f64>(),
+            unrealized,
+            realized,
+        }
+    }
+}
+
+/// A trade's commissions, split the way IB books them: the fills that
+/// opened a held position are in its cost basis, so in unrealized PnL, and
+/// every other fill comes out of realized PnL.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Commissions {
+    pub realized: f64,
+    pub unrealized: f64,
+}
+
+impl Commissions {
+    pub fn of(trade: &Trade, fills: &[Fill]) -> Self {
+        let mut commissions = Commissions::default();
+        for fill in fills {
+            let commission = fill.commission.unwrap_or_default();
+            let bought = matches!(fill.side.as_str(), "BOT" | "BUY");
+            let opening = trade.position != 0 && bought == (trade.position > 0);
+            if opening {
+                commissions.unrealized += commission;
+            } else {
+                commissions.realized += commission;
+            }
+        }
+        commissions
+    }
+
+    /// The trade's entry in `commissions`, keyed by journal id; none for a
+    /// trade not journaled yet.
+    fn find(trade: &Trade, commissions: &HashMap<i64, Commissions>) -> Self {
+        trade
+            .journal_id
+            .and_then(|id| commissions.get(&id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn total(&self) -> f64 {
+        self.realized + self.unrealized
+    }
+}
+
+/// IB's latest PnL for one position.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PositionPnl {
+    pub position: f64,
+    pub pnl: PnlBreakdown,
+}
+
+/// A figure IB and the local books disagree on.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Discrepancy {
+    /// Account or position PnL, e.g. `subject` "Account realized".
+    Pnl { subject: String, ib: f64, ours: f64 },
+    /// IB holds a different number of shares than the trade.
+    Position { symbol: String, ib: f64, ours: i32 },
+}
+
+impl Discrepancy {
+    /// Identifies what disagrees regardless of the amounts, so a warning
+    /// is raised once rather than on every update.
+    pub fn key(&self) -> String {
+        match self {
+            Discrepancy::Pnl { subject, .. } => subject.clone(),
+            Discrepancy::Position { symbol, .. } => format!("{} position", symbol),
+        }
+    }
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::Pnl { subject, ib, ours } => write!(
+                f,
+                "{} PnL: IB {} vs ours {}",
+                subject,
+                format_money(*ib),
+                format_money(*ours)
+            ),
+            Discrepancy::Position { symbol, ib, ours } => {
+                write!(f, "{}: IB holds {} shares vs ours {}", symbol, ib, ours)
+            }
+        }
+    }
+}
+
+/// Where IB and the local books disagree by more than `tolerance`, which
+/// usually means a fill was missed. The account totals also count
+/// positions no trade tracks. Each trade is compared with IB's stream for
+/// its contract; a size difference is reported instead of its PnL.
+pub fn discrepancies(
+    ib_account: Option<&PnlBreakdown>,
+    ib_positions: &HashMap<i64, PositionPnl>,
+    trades: &[Trade],
+    commissions: &HashMap<i64, Commissions>,
+    tolerance: f64,
+) -> Vec<Discrepancy> {
+    let mut found = Vec::new();
+    if let Some(ib) = ib_account {
+        let local = PnlBreakdown::local(trades, commissions);
+        let totals = [
+            ("Account unrealized", ib.unrealized, local.unrealized),
+            ("Account realized", ib.realized, local.realized),
+        ];
+        for (subject, ib, ours) in totals {
+            if (ib - ours).abs() > tolerance {
+                found.push(Discrepancy::Pnl {
+                    subject: subject.to_string(),
+                    ib,
+                    ours,
+                });
+            }
+        }
+    }
+
+    for trade in trades.iter().filter(|trade| trade.stage != Stage::Close) {
+        let Some(ib) = ib_positions.get(&trade.contract_id) else {
+            continue;
+        };
+        if ib.position != trade.position as f64 {
+            found.push(Discrepancy::Position {
+                symbol: trade.symbol.clone(),
+                ib: ib.position,
+                ours: trade.position,
+            });
+            continue;
+        }
+        let ours = trade.calculate_pnl() - Commissions::find(trade, commissions).unrealized;
+        if trade.position != 0 && (ib.pnl.unrealized - ours).abs() > tolerance {
+            found.push(Discrepancy::Pnl {
+                subject: format!("{} unrealized", trade.symbol),
+                ib: ib.pnl.unrealized,
+                ours,
+            });
+        }
+    }
+    found
+}
+
+/// IB's real-time PnL streams for one account: the account total and one
+/// stream per watched contract. Polled without blocking.
+pub struct PnlFeed<'a> {
+    client: &'a Client,
+    account_id: String,
+    account: Subscription<'a, PnL>,
+    positions: Vec<(i64, Subscription<'a, PnLSingle>)>,
+    pub account_pnl: Option<PnlBreakdown>,
+    pub position_pnl: HashMap<i64, PositionPnl>,
+}
+
+impl<'a> PnlFeed<'a> {
+    pub fn subscribe(client: &'a Client, account_id: &str) -> Result<Self> {
+        Ok(PnlFeed {
+            client,
+            account_id: account_id.to_string(),
+            account: client.pnl(account_id, None)?,
+            positions: Vec::new(),
+            account_pnl: None,
+            position_pnl: HashMap::new(),
+        })
+    }
+
+    /// Streams the contract's PnL. Contracts already watched or not yet
+    /// resolved (id 0) are skipped.
+    pub fn watch(&mut self, contract_id: i64) -> Result<()> {
+        if contract_id == 0 || self.positions.iter().any(|(id, _)| *id == contract_id) {
+            return Ok(());
+        }
+        let subscription = self
+            .client
+            .pnl_single(&self.account_id, contract_id as i32, None)?;
+        self.positions.push((contract_id, subscription));
+        Ok(())
+    }
+
+    /// Takes the updates received so far and returns the newest account
+    /// update, if any.
+    pub fn poll(&mut self) -> Option<PnL> {
+        let mut latest = None;
+        while let Some(pnl) = self.account.try_next() {
+            self.account_pnl = Some(PnlBreakdown::from_account(&pnl));
+            latest = Some(pnl);
+        }
+        for (contract_id, subscription) in &self.positions {
+            while let Some(pnl) = subscription.try_next() {
+                self.position_pnl.insert(
+                    *contract_id,
+                    PositionPnl {
+                        position: pnl.position,
+                        pnl: PnlBreakdown::from_position(&pnl),
+                    },
+                );
+            }
+        }
+        latest
+    }
+
+    pub fn cancel(&self) {
+        self.account.cancel();
+        for (_, subscription) in &self.positions {
+            subscription.cancel();
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::error::{Error, Result};
+use crate::session::MarketSession;
+use crate::trade::Trade;
+use ibapi::contracts::tick_types::TickType;
+use ibapi::contracts::Contract;
+use ibapi::market_data::realtime::TickTypes;
+use ibapi::Client;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// How long to wait for TWS to finish a snapshot. IB completes snapshots
+/// within about 11 seconds.
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Top of book and day statistics from one market data snapshot. Prices
+/// are `None` when IB sent no tick for them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Quote {
+    pub symbol: String,
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+    pub last: Option<f64>,
+    /// Previous session's close.
+    pub close: Option<f64>,
+    pub volume: Option<f64>,
+    pub session: Option<MarketSession>,
+}
+
+impl Quote {
+    pub fn new(symbol: String) -> Self {
+        Quote {
+            symbol,
+            ..Default::default()
+        }
+    }
+
+    /// Records a snapshot tick; live and delayed ticks are treated alike.
+    pub fn apply(&mut self, tick: &TickTypes) {
+        let (tick_type, value) = match tick {
+            TickTypes::Price(tick) => (&tick.tick_type, tick.price),
+            TickTypes::PriceSize(tick) => (&tick.price_tick_type, tick.price),
+            TickTypes::Size(tick) => (&tick.tick_type, tick.size),
+            _ => return,
+        };
+        if value <= 0.0 {
+            return;
+        }
+        match tick_type {
+            TickType::Bid | TickType::DelayedBid => self.bid = Some(value),
+            TickType::Ask | TickType::DelayedAsk => self.ask = Some(value),
+            TickType::Last | TickType::DelayedLast => self.last = Some(value),
+            TickType::Close | TickType::DelayedClose => self.close = Some(value),
+            TickType::Volume | TickType::DelayedVolume => self.volume = Some(value),
+            _ => {}
+        }
+    }
+
+    /// Last trade price, or the previous close when nothing has traded yet.
+    pub fn price(&self) -> Option<f64> {
+        self.last.or(self.close)
+    }
+
+    /// Change of the last price from the previous close.
+    pub fn change(&self) -> Option<f64> {
+        Some(self.last? - self.close?)
+    }
+
+    pub fn change_percent(&self) -> Option<f64> {
+        Some(self.change()? / self.close? * 100.0)
+    }
+}
+
+/// One row of `ibxrust quote`: the quote, or why the symbol failed.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum QuoteRow {
+    Quote(Quote),
+    Failed { symbol: String, error: String },
+}
+
+/// Quotes each symbol in turn. A symbol that fails becomes a `Failed` row
+/// instead of stopping the batch.
+pub fn fetch_all(client: &Client, symbols: &[String]) -> Vec<QuoteRow> {
+    symbols
+        .iter()
+        .map(|symbol| match fetch(client, symbol) {
+            Ok(quote) => QuoteRow::Quote(quote),
+            Err(e) => QuoteRow::Failed {
+                symbol: symbol.clone(),
+                error: e.to_string(),
+            },
+        })
+        .collect()
+}
+
+/// Resolves the symbol's contract like a new trade does, then takes a
+/// snapshot and the current session from its trading hours.
+pub fn fetch(client: &Client, symbol: &str) -> Result<Quote> {
+    let mut trade = Trade::new(symbol.to_uppercase());
+    trade.create_contract();
+    // Contract details fail for unknown symbols, before any market data
+    trade.resolve_contract(client)?;
+    let contract = trade
+        .contract
+        .as_ref()
+        .ok_or_else(|| Error::MarketData(format!("{}: contract not created", symbol)))?;
+    let mut quote = snapshot(client, contract)?;
+    quote.session = trade.market_session();
+    Ok(quote)
+}
+
+/// Takes a market data snapshot of the contract.
+pub fn snapshot(client: &Client, contract: &Contract) -> Result<Quote> {
+    let subscription = client.market_data(contract, &[], true, false)?;
+    let mut quote = Quote::new(contract.symbol.clone());
+    let deadline = Instant::now() + SNAPSHOT_TIMEOUT;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::MarketData(format!(
+                "{}: market data snapshot timed out",
+                contract.symbol
+            )));
+        }
+        match subscription.next_timeout(remaining) {
+            Some(TickTypes::SnapshotEnd) => break,
+            Some(TickTypes::Notice(notice)) => {
+                tracing::debug!("{}: {}", contract.symbol, notice.message)
+            }
+            Some(tick) => quote.apply(&tick),
+            None => {}
+        }
+    }
+    Ok(quote)
+}
@@ -0,0 +1,203 @@
+use crate::error::Result;
+use crate::journal::{Journal, TradeRecord};
+use crate::trade::{Stage, Trade};
+use ibapi::accounts::{Position, PositionUpdate};
+use ibapi::contracts::Contract;
+use ibapi::orders::{Action, OrderData, Orders};
+use ibapi::Client;
+use serde::Serialize;
+
+/// Outcome of reconciling journaled trades against TWS after a restart.
+#[derive(Debug, Default, Serialize)]
+pub struct Recovery {
+    /// Trades that are still live and can be managed again.
+    pub trades: Vec<Trade>,
+    /// Trades whose position is gone at IB; they are marked closed.
+    pub closed: Vec<Trade>,
+    /// Discrepancies the user should look at.
+    pub issues: Vec<String>,
+}
+
+/// Loads unfinished trades from the journal, reconciles them with IB
+/// positions and open orders, and writes the corrected state back.
+pub fn recover(client: &Client, journal: &Journal) -> Result<Recovery> {
+    let records = journal.unfinished_trades()?;
+    if records.is_empty() {
+        return Ok(Recovery::default());
+    }
+
+    let mut recovery = reconcile(
+        &records,
+        &fetch_positions(client)?,
+        &fetch_open_orders(client)?,
+    );
+    for trade in &recovery.closed {
+        journal.update_stage(trade)?;
+    }
+    for trade in &mut recovery.trades {
+        if let Some(excursion) = trade
+            .journal_id
+            .map(|id| journal.excursion(id))
+            .transpose()?
+            .flatten()
+        {
+            trade.excursion = excursion;
+        }
+        let record = records
+            .iter()
+            .find(|record| Some(record.id) == trade.journal_id);
+        if record.is_some_and(|record| record.stage != trade.stage) {
+            journal.update_stage(trade)?;
+        }
+        if record.is_some_and(|record| record.stop_price != trade.stop_price) {
+            journal.update_stop(trade)?;
+        }
+    }
+    Ok(recovery)
+}
+
+pub fn reconcile(
+    records: &[TradeRecord],
+    positions: &[Position],
+    open_orders: &[OrderData],
+) -> Recovery {
+    let mut recovery = Recovery::default();
+
+    for record in records {
+        let matches = |contract: &Contract, account: &str| {
+            // Trades journaled before accounts were recorded match any account
+            let same_account = record
+                .account
+                .as_deref()
+                .is_none_or(|journaled| account.is_empty() || journaled == account);
+            let same_contract = if record.contract_id != 0 {
+                contract.contract_id as i64 == record.contract_id
+            } else {
+                contract.symbol == record.symbol
+            };
+            same_account && same_contract
+        };
+
+        let mut trade = Trade::new(record.symbol.clone());
+        trade.journal_id = Some(record.id);
+        trade.contract_id = record.contract_id;
+        trade.account = record.account.clone();
+        trade.strategy = record.strategy.clone();
+        trade.entry_price = record.entry_price;
+        trade.realized_pnl = record.realized_pnl.unwrap_or_default();
+        trade.stop_price = record.stop_price;
+        trade.opened_at = Some(record.opened_at);
+        trade.create_contract();
+
+        let position = positions.iter().find(|position| {
+            matches(&position.contract, &position.account) && position.position != 0.0
+        });
+        let working: Vec<&OrderData> = open_orders
+            .iter()
+            .filter(|order| matches(&order.contract, &order.order.account))
+            .collect();
+
+        let Some(position) = position else {
+            // Entries are journaled with no shares until they fill
+            let entry = record.stage == Stage::Open
+                || (record.stage == Stage::Disconnect && record.shares == 0);
+            let entry_order = working
+                .iter()
+                .find(|order| entry && order.order.action == Action::Buy);
+            if let Some(entry_order) = entry_order {
+                // Entry order still working, nothing filled yet
+                trade.stage = Stage::Open;
+                trade.entry_order_id = Some(entry_order.order_id);
+                recovery.trades.push(trade);
+            } else {
+                recovery.issues.push(format!(
+                    "{}: no open position at IB, marking journaled trade #{} closed",
+                    record.symbol, record.id
+                ));
+                trade.stage = Stage::Close;
+                recovery.closed.push(trade);
+            }
+            continue;
+        };
+
+        trade.contract = Some(position.contract.clone());
+        trade.contract_id = position.contract.contract_id as i64;
+        trade.account = Some(position.account.clone());
+        trade.position = position.position as i32;
+        trade.stage = Stage::Hold;
+        if trade.position != record.shares {
+            recovery.issues.push(format!(
+                "{}: journal has {} shares but IB reports {}, using IB position",
+                record.symbol, record.shares, trade.position
+            ));
+        }
+        if trade.entry_price == 0.0 {
+            trade.entry_price = position.average_cost;
+        }
+
+        let exit_action = if trade.position > 0 {
+            Action::Sell
+        } else {
+            Action::Buy
+        };
+        let stop = working.iter().find(|order| {
+            order.order.action == exit_action && order.order.order_type.starts_with("STP")
+        });
+        match stop {
+            Some(stop) => {
+                trade.stop_order_id = Some(stop.order_id);
+                trade.stop_price = stop.order.aux_price.or(trade.stop_price);
+            }
+            None if record.stop_price.is_some() => {
+                recovery.issues.push(format!(
+                    "{}: journaled stop at ${:.2} is no longer working at IB",
+                    record.symbol,
+                    record.stop_price.unwrap_or_default()
+                ));
+            }
+            None => {}
+        }
+
+        recovery.trades.push(trade);
+    }
+
+    for position in positions.iter().filter(|position| position.position != 0.0) {
+        let tracked = recovery.trades.iter().any(|trade| {
+            trade.account.as_deref() == Some(position.account.as_str())
+                && (trade.contract_id == position.contract.contract_id as i64
+                    || trade.symbol == position.contract.symbol)
+        });
+        if !tracked {
+            recovery.issues.push(format!(
+                "{}: IB position of {} in {} is not tracked by any journaled trade",
+                position.contract.symbol, position.position, position.account
+            ));
+        }
+    }
+
+    recovery
+}
+
+pub fn fetch_positions(client: &Client) -> Result<Vec<Position>> {
+    let subscription = client.positions()?;
+    let mut positions = Vec::new();
+    for update in &subscription {
+        match update {
+            PositionUpdate::Position(position) => positions.push(position),
+            PositionUpdate::PositionEnd => break,
+        }
+    }
+    subscription.cancel();
+    Ok(positions)
+}
+
+pub fn fetch_open_orders(client: &Client) -> Result<Vec<OrderData>> {
+    let subscription = client.all_open_orders()?;
+    let mut orders = Vec::new();
+    for update in &subscription {
+        if let Orders::OrderData(order) = update {
+            orders.push(order);
+        }
+    }
+    Ok(orders)
+}
@@ -0,0 +1,146 @@
+use crate::error::{Error, Result};
+use ibapi::orders::Action;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskLimits {
+    pub max_shares_per_order: i32,
+    pub max_notional_per_order: f64,
+    pub max_position_per_symbol: i32,
+    pub max_price_deviation_pct: f64,
+    pub max_orders_per_minute: usize,
+}
+
+impl Default for RiskLimits {
+    fn default() -> Self {
+        RiskLimits {
+            max_shares_per_order: 1_000,
+            max_notional_per_order: 50_000.0,
+            max_position_per_symbol: 2_000,
+            max_price_deviation_pct: 5.0,
+            max_orders_per_minute: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderCheck<'a> {
+    pub symbol: &'a str,
+    pub action: Action,
+    pub shares: i32,
+    pub limit_price: Option<f64>,
+    pub last_price: f64,
+    pub current_position: i32,
+}
+
+/// Pre-trade checks applied to every order before it is sent to TWS.
+///
+/// Limits are fixed when the engine is built from `Config`; there is no way
+/// to relax them afterwards.
+#[derive(Debug)]
+pub struct RiskEngine {
+    limits: RiskLimits,
+    recent_orders: VecDeque<Instant>,
+}
+
+impl RiskEngine {
+    pub fn new(limits: RiskLimits) -> Self {
+        RiskEngine {
+            limits,
+            recent_orders: VecDeque::new(),
+        }
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    /// Checks an order against every limit. Passing doesn't use up the
+    /// rate limit; call `record_sent` once the order is actually placed.
+    pub fn check(&mut self, order: &OrderCheck) -> Result<()> {
+        self.check_at(order, Instant::now())
+    }
+
+    pub fn check_at(&mut self, order: &OrderCheck, now: Instant) -> Result<()> {
+        let limits = &self.limits;
+
+        if order.shares <= 0 {
+            return Err(Error::Risk(format!("{}: order quantity must be positive", order.symbol)));
+        }
+        if order.shares > limits.max_shares_per_order {
+            return Err(Error::Risk(format!(
+                "{}: {} shares exceeds max {} shares per order",
+                order.symbol, order.shares, limits.max_shares_per_order
+            )));
+        }
+
+        let price = order.limit_price.unwrap_or(order.last_price);
+        if price <= 0.0 {
+            return Err(Error::Risk(format!("{}: no valid price to check order against", order.symbol)));
+        }
+        let notional = price * order.shares as f64;
+        if notional > limits.max_notional_per_order {
+            return Err(Error::Risk(format!(
+                "{}: notional ${:.2} exceeds max ${:.2} per order",
+                order.symbol, notional, limits.max_notional_per_order
+            )));
+        }
+
+        let signed_shares = match order.action {
+            Action::Buy => order.shares,
+            _ => -order.shares,
+        };
+        let resulting_position = order.current_position + signed_shares;
+        if resulting_position.abs() > limits.max_position_per_symbol {
+            return Err(Error::Risk(format!(
+                "{}: resulting position {} exceeds max {} shares per symbol",
+                order.symbol, resulting_position, limits.max_position_per_symbol
+            )));
+        }
+
+        if let Some(limit_price) = order.limit_price {
+            if order.last_price <= 0.0 {
+                return Err(Error::Risk(format!(
+                    "{}: no last price to validate limit ${:.2} against",
+                    order.symbol, limit_price
+                )));
+            }
+            let deviation = (limit_price - order.last_price).abs() / order.last_price * 100.0;
+            if deviation > limits.max_price_deviation_pct {
+                return Err(Error::Risk(format!(
+                    "{}: limit ${:.2} is {:.1}% from last ${:.2} (max {:.1}%)",
+                    order.symbol, limit_price, deviation, order.last_price, limits.max_price_deviation_pct
+                )));
+            }
+        }
+
+        while let Some(sent) = self.recent_orders.front() {
+            if now.duration_since(*sent) >= RATE_WINDOW {
+                self.recent_orders.pop_front();
+            } else {
+                break;
+            }
+        }
+        if self.recent_orders.len() >= limits.max_orders_per_minute {
+            return Err(Error::Risk(format!(
+                "order rate limit of {} per minute reached",
+                limits.max_orders_per_minute
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Counts an order placed with TWS against the per-minute rate limit.
+    pub fn record_sent(&mut self) {
+        self.record_sent_at(Instant::now());
+    }
+
+    pub fn record_sent_at(&mut self, now: Instant) {
+        self.recent_orders.push_back(now);
+    }
+}
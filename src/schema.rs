@@ -0,0 +1,92 @@
+use crate::error::Result;
+use ibapi::contracts::{Contract, SecurityType};
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON documents produced by `--json` and the `to_json`
+/// helpers. Bump it when a field is renamed or removed; adding optional
+/// fields is backwards compatible.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Top-level JSON document: the payload tagged with the schema version.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub schema_version: u32,
+    pub data: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(data: T) -> Self {
+        Envelope {
+            schema_version: SCHEMA_VERSION,
+            data,
+        }
+    }
+}
+
+pub fn to_json<T: Serialize>(data: &T) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&Envelope::new(data))?)
+}
+
+/// Serializable mirror of the ibapi `Contract` fields we rely on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractDef {
+    pub contract_id: i32,
+    pub symbol: String,
+    pub security_type: String,
+    pub exchange: String,
+    pub primary_exchange: String,
+    pub currency: String,
+    pub local_symbol: String,
+}
+
+impl From<&Contract> for ContractDef {
+    fn from(contract: &Contract) -> Self {
+        ContractDef {
+            contract_id: contract.contract_id,
+            symbol: contract.symbol.clone(),
+            security_type: contract.security_type.to_string(),
+            exchange: contract.exchange.clone(),
+            primary_exchange: contract.primary_exchange.clone(),
+            currency: contract.currency.clone(),
+            local_symbol: contract.local_symbol.clone(),
+        }
+    }
+}
+
+impl From<ContractDef> for Contract {
+    fn from(def: ContractDef) -> Self {
+        Contract {
+            contract_id: def.contract_id,
+            symbol: def.symbol,
+            security_type: SecurityType::from(&def.security_type),
+            exchange: def.exchange,
+            primary_exchange: def.primary_exchange,
+            currency: def.currency,
+            local_symbol: def.local_symbol,
+            ..Default::default()
+        }
+    }
+}
+
+/// `#[serde(with = "crate::schema::contract_option")]` for `Option<Contract>` fields.
+pub mod contract_option {
+    use super::ContractDef;
+    use ibapi::contracts::Contract;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        contract: &Option<Contract>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        contract
+            .as_ref()
+            .map(ContractDef::from)
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Contract>, D::Error> {
+        Ok(Option::<ContractDef>::deserialize(deserializer)?.map(Contract::from))
+    }
+}
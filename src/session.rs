@@ -0,0 +1,181 @@
+use crate::error::{Error, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use ibapi::contracts::ContractDetails;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketSession {
+    PreMarket,
+    Regular,
+    AfterHours,
+    Closed,
+}
+
+impl MarketSession {
+    pub fn is_regular(&self) -> bool {
+        *self == MarketSession::Regular
+    }
+}
+
+impl fmt::Display for MarketSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            MarketSession::PreMarket => "Pre-market",
+            MarketSession::Regular => "Regular hours",
+            MarketSession::AfterHours => "After hours",
+            MarketSession::Closed => "Closed",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// What to do with a market order sent outside regular trading hours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutsideRthPolicy {
+    Warn,
+    Block,
+}
+
+impl std::str::FromStr for OutsideRthPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "warn" => Ok(OutsideRthPolicy::Warn),
+            "block" => Ok(OutsideRthPolicy::Block),
+            other => Err(Error::Config(format!("expected warn or block, got {:?}", other))),
+        }
+    }
+}
+
+impl OutsideRthPolicy {
+    /// Applies the policy to a market order in `session`. Without a
+    /// schedule the session is unknown, which fails closed: `Block` refuses
+    /// it like any session outside regular hours.
+    pub fn check_market_order(&self, symbol: &str, session: Option<MarketSession>) -> Result<()> {
+        let session = match session {
+            Some(session) if session.is_regular() => return Ok(()),
+            Some(session) => session.to_string(),
+            None => "trading hours unknown".to_string(),
+        };
+        match self {
+            OutsideRthPolicy::Block => Err(Error::Order(format!(
+                "{}: market orders are blocked outside regular hours ({})",
+                symbol, session
+            ))),
+            OutsideRthPolicy::Warn => {
+                tracing::warn!(
+                    "{}: sending market order outside regular hours ({})",
+                    symbol,
+                    session
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Trading calendar of one contract, in the exchange's time zone.
+///
+/// `trading_hours` covers the whole tradable day including extended hours,
+/// `liquid_hours` only the regular session.
+#[derive(Debug, Clone)]
+pub struct TradingSchedule {
+    pub time_zone: Tz,
+    pub trading_hours: Vec<(NaiveDateTime, NaiveDateTime)>,
+    pub liquid_hours: Vec<(NaiveDateTime, NaiveDateTime)>,
+}
+
+impl TradingSchedule {
+    pub fn from_details(details: &ContractDetails) -> Result<Self> {
+        let time_zone = details
+            .time_zone_id
+            .parse::<Tz>()
+            .map_err(|e| Error::MarketData(format!("Unknown time zone {:?}: {}", details.time_zone_id, e)))?;
+        Ok(TradingSchedule {
+            time_zone,
+            trading_hours: parse_hours(&details.trading_hours)?,
+            liquid_hours: parse_hours(&details.liquid_hours)?,
+        })
+    }
+
+    pub fn session_now(&self) -> MarketSession {
+        self.session_at(Utc::now())
+    }
+
+    /// When the regular session `now` falls in opened, in exchange time;
+    /// `None` outside regular hours. A new value means a new session.
+    pub fn regular_session_start(&self, now: DateTime<Utc>) -> Option<NaiveDateTime> {
+        let local = now.with_timezone(&self.time_zone).naive_local();
+        self.liquid_hours
+            .iter()
+            .find(|(start, end)| *start <= local && local < *end)
+            .map(|(start, _)| *start)
+    }
+
+    pub fn session_at(&self, now: DateTime<Utc>) -> MarketSession {
+        let local = now.with_timezone(&self.time_zone).naive_local();
+        let within = |ranges: &[(NaiveDateTime, NaiveDateTime)]| {
+            ranges.iter().find(|(start, end)| *start <= local && local < *end).copied()
+        };
+
+        if within(&self.liquid_hours).is_some() {
+            return MarketSession::Regular;
+        }
+        let Some((trading_start, trading_end)) = within(&self.trading_hours) else {
+            return MarketSession::Closed;
+        };
+
+        // Extended hours before the first regular session of this trading window
+        // are pre-market, anything after it is after-hours.
+        let regular_start = self
+            .liquid_hours
+            .iter()
+            .map(|(start, _)| *start)
+            .filter(|start| *start >= trading_start && *start < trading_end)
+            .min();
+        match regular_start {
+            Some(start) if local < start => MarketSession::PreMarket,
+            Some(_) => MarketSession::AfterHours,
+            None => MarketSession::Closed,
+        }
+    }
+}
+
+/// Parses IB session strings such as `20240102:0400-20240102:2000;20240103:CLOSED`
+/// and the older `20090507:0700-1830,1830-2330` form.
+pub fn parse_hours(hours: &[String]) -> Result<Vec<(NaiveDateTime, NaiveDateTime)>> {
+    let mut ranges = Vec::new();
+    for day in hours.iter().flat_map(|entry| entry.split(';')) {
+        let day = day.trim();
+        if day.is_empty() || day.ends_with("CLOSED") {
+            continue;
+        }
+        let (date, spans) = match day.split_once(':') {
+            Some((date, spans)) if !spans.contains(':') => (Some(date), spans),
+            _ => (None, day),
+        };
+        for span in spans.split(',') {
+            let (start, end) = span
+                .split_once('-')
+                .ok_or_else(|| Error::MarketData(format!("Invalid trading hours {:?}", day)))?;
+            ranges.push((parse_point(start, date)?, parse_point(end, date)?));
+        }
+    }
+    Ok(ranges)
+}
+
+fn parse_point(point: &str, default_date: Option<&str>) -> Result<NaiveDateTime> {
+    let invalid = || Error::MarketData(format!("Invalid trading hours time {:?}", point));
+    let (date, time) = match point.split_once(':') {
+        Some((date, time)) => (date, time),
+        None => (default_date.ok_or_else(invalid)?, point),
+    };
+    let date = NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| invalid())?;
+    let time = NaiveTime::parse_from_str(time, "%H%M").map_err(|_| invalid())?;
+    Ok(date.and_time(time))
+}
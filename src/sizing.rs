@@ -0,0 +1,152 @@
+use crate::error::{Error, Result};
+use ibapi::accounts::{AccountSummaries, AccountSummaryTags};
+use ibapi::Client;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// How many shares to buy when a position is opened.
+///
+/// The text form is what `SIZING_MODE` and the trade prompt accept:
+/// `100` (shares), `$5000` (dollar amount), `2%` (of net liquidation)
+/// and `risk:$200` (dollars at risk divided by the stop distance).
+#[derive(Debug, Clone, PartialEq)]
+pub enum SizingMode {
+    FixedShares(i32),
+    FixedDollars(f64),
+    PercentOfNetLiq(f64),
+    RiskBudget(f64),
+}
+
+impl SizingMode {
+    /// Risk-based sizing divides by the stop distance, and entries only get
+    /// a stop from `ATR_STOP_MULTIPLE`, so without one it can't size a buy.
+    pub fn check_stop_source(&self, atr_stop_multiple: Option<f64>) -> Result<()> {
+        match (self, atr_stop_multiple) {
+            (SizingMode::RiskBudget(_), None) => Err(Error::Sizing(format!(
+                "{} needs ATR_STOP_MULTIPLE to give entries a stop",
+                self
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn shares(&self, price: f64, stop_price: Option<f64>, net_liquidation: Option<f64>) -> Result<i32> {
+        if let SizingMode::FixedShares(shares) = self {
+            return if *shares > 0 {
+                Ok(*shares)
+            } else {
+                Err(Error::Sizing(format!("Share count must be positive, got {}", shares)))
+            };
+        }
+
+        if price <= 0.0 {
+            return Err(Error::Sizing(format!("Invalid price for sizing: {}", price)));
+        }
+
+        let shares = match self {
+            SizingMode::FixedShares(_) => unreachable!(),
+            SizingMode::FixedDollars(amount) => amount / price,
+            SizingMode::PercentOfNetLiq(percent) => {
+                let net_liquidation = net_liquidation
+                    .ok_or_else(|| Error::Sizing("Net liquidation value is not available".to_string()))?;
+                net_liquidation * percent / 100.0 / price
+            }
+            SizingMode::RiskBudget(risk) => {
+                let stop_price = stop_price
+                    .ok_or_else(|| Error::Sizing("Risk-based sizing requires a stop price".to_string()))?;
+                let stop_distance = (price - stop_price).abs();
+                if stop_distance == 0.0 {
+                    return Err(Error::Sizing("Stop price equals entry price".to_string()));
+                }
+                risk / stop_distance
+            }
+        };
+
+        let shares = shares.floor();
+        if shares < 1.0 {
+            return Err(Error::Sizing(format!("{} buys less than one share at ${:.2}", self, price)));
+        }
+        if shares > i32::MAX as f64 {
+            return Err(Error::Sizing(format!("{} buys too many shares at ${:.2}", self, price)));
+        }
+        Ok(shares as i32)
+    }
+}
+
+impl fmt::Display for SizingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizingMode::FixedShares(shares) => write!(f, "{}", shares),
+            SizingMode::FixedDollars(amount) => write!(f, "${}", amount),
+            SizingMode::PercentOfNetLiq(percent) => write!(f, "{}%", percent),
+            SizingMode::RiskBudget(risk) => write!(f, "risk:${}", risk),
+        }
+    }
+}
+
+impl FromStr for SizingMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let invalid = || Error::Sizing(format!("Invalid sizing mode: {:?}", s));
+        let parse_amount = |value: &str| {
+            value
+                .trim()
+                .trim_start_matches('$')
+                .replace(',', "")
+                .parse::<f64>()
+                .ok()
+                .filter(|amount| *amount > 0.0)
+                .ok_or_else(invalid)
+        };
+
+        if let Some(risk) = s.strip_prefix("risk:") {
+            Ok(SizingMode::RiskBudget(parse_amount(risk)?))
+        } else if let Some(percent) = s.strip_suffix('%') {
+            Ok(SizingMode::PercentOfNetLiq(parse_amount(percent)?))
+        } else if s.starts_with('$') {
+            Ok(SizingMode::FixedDollars(parse_amount(s)?))
+        } else {
+            s.parse::<i32>()
+                .ok()
+                .filter(|shares| *shares > 0)
+                .map(SizingMode::FixedShares)
+                .ok_or_else(invalid)
+        }
+    }
+}
+
+impl Serialize for SizingMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SizingMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Net liquidation of `account`. The summary request covers every managed
+/// account, so other accounts' rows are skipped.
+pub fn net_liquidation(client: &Client, account: &str) -> Result<f64> {
+    let subscription = client.account_summary("All", &[AccountSummaryTags::NET_LIQUIDATION])?;
+    for update in &subscription {
+        match update {
+            AccountSummaries::Summary(summary) if summary.account == account && summary.tag == AccountSummaryTags::NET_LIQUIDATION => {
+                subscription.cancel();
+                return summary
+                    .value
+                    .parse::<f64>()
+                    .map_err(|e| Error::Sizing(format!("Invalid net liquidation value {:?}: {}", summary.value, e)));
+            }
+            AccountSummaries::End => break,
+            _ => {}
+        }
+    }
+    Err(Error::Sizing(format!("Account summary did not report net liquidation for {}", account)))
+}
@@ -1,6 +1,16 @@
+use crate::error::{Error, Result};
+use crate::excursion::Excursion;
+use crate::session::{MarketSession, TradingSchedule};
+use crate::sizing::SizingMode;
+use chrono::{DateTime, Utc};
 use ibapi::contracts::Contract;
+use ibapi::Client;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Stage {
     Connect,
     Open,
@@ -9,14 +19,65 @@ pub enum Stage {
     Disconnect,
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::Connect => "connect",
+            Stage::Open => "open",
+            Stage::Hold => "hold",
+            Stage::Close => "close",
+            Stage::Disconnect => "disconnect",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Stage {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "connect" => Ok(Stage::Connect),
+            "open" => Ok(Stage::Open),
+            "hold" => Ok(Stage::Hold),
+            "close" => Ok(Stage::Close),
+            "disconnect" => Ok(Stage::Disconnect),
+            other => Err(Error::Other(format!("Unknown trade stage: {}", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub symbol: String,
     pub contract_id: i64,
+    /// IB account the trade belongs to.
+    #[serde(default)]
+    pub account: Option<String>,
     pub position: i32,
     pub entry_price: f64,
     pub current_price: f64,
+    pub stop_price: Option<f64>,
+    /// Profit target, shown on the chart.
+    #[serde(default)]
+    pub target_price: Option<f64>,
+    pub stop_order_id: Option<i32>,
+    /// Entry order found still working at IB when the trade was recovered.
+    #[serde(default)]
+    pub entry_order_id: Option<i32>,
+    pub exit_price: Option<f64>,
+    pub realized_pnl: f64,
+    pub opened_at: Option<DateTime<Utc>>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub strategy: Option<String>,
+    pub journal_id: Option<i64>,
+    pub sizing: Option<SizingMode>,
+    #[serde(default)]
+    pub excursion: Excursion,
+    #[serde(with = "crate::schema::contract_option")]
     pub contract: Option<Contract>,
+    #[serde(skip)]
+    pub schedule: Option<TradingSchedule>,
     pub stage: Stage,
 }
 
@@ -25,10 +86,24 @@ impl Trade {
         Trade {
             symbol,
             contract_id: 0,
+            account: None,
             position: 0,
             entry_price: 0.0,
             current_price: 0.0,
+            stop_price: None,
+            target_price: None,
+            stop_order_id: None,
+            entry_order_id: None,
+            exit_price: None,
+            realized_pnl: 0.0,
+            opened_at: None,
+            closed_at: None,
+            strategy: None,
+            journal_id: None,
+            sizing: None,
+            excursion: Excursion::default(),
             contract: None,
+            schedule: None,
             stage: Stage::Connect,
         }
     }
@@ -38,6 +113,29 @@ impl Trade {
         self.contract = Some(contract);
     }
 
+    /// Looks the contract up at IB, filling in its contract id and the
+    /// trading schedule. Fails for symbols IB doesn't know.
+    pub fn resolve_contract(&mut self, client: &Client) -> Result<()> {
+        let contract = self
+            .contract
+            .as_mut()
+            .ok_or_else(|| Error::MarketData(format!("{}: contract not created", self.symbol)))?;
+        let details = client.contract_details(contract)?;
+        let details = details.first().ok_or_else(|| {
+            Error::MarketData(format!("No contract details for {}", contract.symbol))
+        })?;
+        contract.contract_id = details.contract.contract_id;
+        self.contract_id = details.contract.contract_id as i64;
+        self.schedule = Some(TradingSchedule::from_details(details)?);
+        Ok(())
+    }
+
+    pub fn market_session(&self) -> Option<MarketSession> {
+        self.schedule
+            .as_ref()
+            .map(|schedule| schedule.session_now())
+    }
+
     pub fn calculate_pnl(&self) -> f64 {
         if self.position == 0 {
             return 0.0;
@@ -46,17 +144,86 @@ impl Trade {
     }
 
     pub fn update_price(&mut self, price: f64) {
+        self.update_price_at(price, Utc::now());
+    }
+
+    /// Like `update_price`, with the tick time given. Excursions are only
+    /// tracked while the position is held.
+    pub fn update_price_at(&mut self, price: f64, at: DateTime<Utc>) {
         self.current_price = price;
+        if self.stage == Stage::Hold {
+            let pnl = self.calculate_pnl();
+            self.excursion.record(price, pnl, at);
+        }
+    }
+
+    /// A stop `multiple` ATRs from the entry, or from the current price
+    /// while flat, on the losing side of the position. Flat trades are
+    /// treated as longs, since entries are buys.
+    pub fn atr_stop(&self, atr: f64, multiple: f64) -> f64 {
+        let distance = atr * multiple;
+        match self.position {
+            0 => self.current_price - distance,
+            position if position > 0 => self.entry_price - distance,
+            _ => self.entry_price + distance,
+        }
+    }
+
+    /// A target `multiple` ATRs from the entry on the winning side, the
+    /// mirror of `atr_stop`.
+    pub fn atr_target(&self, atr: f64, multiple: f64) -> f64 {
+        self.atr_stop(atr, -multiple)
+    }
+
+    /// Sets the per-trade sizing override from an answer typed at the
+    /// prompt, in `SizingMode` syntax. An empty answer clears it.
+    pub fn set_sizing(&mut self, input: &str) -> Result<()> {
+        self.sizing = match input.trim() {
+            "" => None,
+            input => Some(input.parse()?),
+        };
+        Ok(())
+    }
+
+    /// Shares to buy at the current price, using the per-trade sizing
+    /// override if one was entered at the prompt, else `default_mode`.
+    pub fn shares_to_buy(
+        &self,
+        default_mode: &SizingMode,
+        net_liquidation: Option<f64>,
+    ) -> Result<i32> {
+        self.sizing.as_ref().unwrap_or(default_mode).shares(
+            self.current_price,
+            self.stop_price,
+            net_liquidation,
+        )
     }
 
     pub fn open_position(&mut self, shares: i32, price: f64) {
         self.position = shares;
         self.entry_price = price;
+        self.exit_price = None;
+        self.opened_at = Some(Utc::now());
+        self.closed_at = None;
+        self.excursion.reset();
         self.stage = Stage::Hold;
     }
 
+    /// Closes `shares` of the position at the current price and banks their
+    /// PnL; the rest stays held at the same entry price.
+    pub fn reduce_position(&mut self, shares: i32) -> f64 {
+        let closed = shares.min(self.position.abs()) * self.position.signum();
+        let pnl = (self.current_price - self.entry_price) * closed as f64;
+        self.realized_pnl += pnl;
+        self.position -= closed;
+        pnl
+    }
+
     pub fn close_position(&mut self) -> f64 {
         let pnl = self.calculate_pnl();
+        self.realized_pnl += pnl;
+        self.exit_price = Some(self.current_price);
+        self.closed_at = Some(Utc::now());
         self.position = 0;
         self.entry_price = 0.0;
         self.stage = Stage::Close;
@@ -0,0 +1,366 @@
+use crate::account::{Account, PnlCheck};
+use crate::error::Result;
+use crate::pnl::PnlBreakdown;
+use crate::trade::{Stage, Trade};
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use crossterm::{cursor, execute, queue, terminal};
+use std::collections::VecDeque;
+use std::io::{self, Stdout, Write};
+use std::sync::Once;
+
+pub const ORANGE: Color = Color::Rgb {
+    r: 255,
+    g: 165,
+    b: 0,
+};
+
+const MAX_MESSAGES: usize = 8;
+
+/// One row of the screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub text: String,
+    pub color: Option<Color>,
+}
+
+impl Line {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Line {
+            text: text.into(),
+            color: None,
+        }
+    }
+
+    pub fn colored(text: impl Into<String>, color: Color) -> Self {
+        Line {
+            text: text.into(),
+            color: Some(color),
+        }
+    }
+}
+
+pub fn format_money(amount: f64) -> String {
+    if amount < 0.0 {
+        format!("-${:.2}", -amount)
+    } else {
+        format!("${:.2}", amount)
+    }
+}
+
+/// `*** PnL: $80.00`, green for a profit and red for a loss.
+pub fn pnl_line(pnl: f64) -> Line {
+    let color = if pnl < 0.0 { Color::Red } else { Color::Green };
+    Line::colored(format!("*** PnL: {}", format_money(pnl)), color)
+}
+
+/// `*** AAPL : $160.00` in orange.
+pub fn price_line(symbol: &str, price: f64) -> Line {
+    Line::colored(format!("*** {} : ${:.2}", symbol, price), ORANGE)
+}
+
+/// Buy when flat, Sell while holding, nothing to answer while an order works.
+pub fn prompt_line(trade: &Trade, order_working: bool) -> Line {
+    let text = match trade.stage {
+        _ if order_working => ">> Order working...",
+        Stage::Hold => ">> Sell (y/n) ?",
+        Stage::Open => ">> Order working...",
+        Stage::Disconnect => ">> Disconnected",
+        Stage::Connect | Stage::Close => ">> Buy (y/n) ?",
+    };
+    Line::plain(text)
+}
+
+/// A watchlist row as drawn: the trade, whether it has an order working
+/// and its indicators, if they have enough data yet.
+pub struct WatchRow<'a> {
+    pub trade: &'a Trade,
+    pub order_working: bool,
+    pub vwap: Option<f64>,
+    pub atr: Option<f64>,
+}
+
+/// Watchlist table with a header row. The selected row is marked with `>`.
+pub fn watchlist_lines(rows: &[WatchRow], selected: usize) -> Vec<Line> {
+    let mut lines = vec![Line::plain(format!(
+        "  {:<8} {:>10} {:>8} {:>10} {:>11}  {}",
+        "Symbol", "Last", "Position", "Entry", "PnL", "Stage"
+    ))];
+    for (index, row) in rows.iter().enumerate() {
+        let trade = row.trade;
+        let pnl = trade.realized_pnl + trade.calculate_pnl();
+        let stage = if row.order_working {
+            "working".to_string()
+        } else {
+            trade.stage.to_string()
+        };
+        let text = format!(
+            "{} {:<8} {:>10.2} {:>8} {:>10.2} {:>11}  {}",
+            if index == selected { ">" } else { " " },
+            trade.symbol,
+            trade.current_price,
+            trade.position,
+            trade.entry_price,
+            format_money(pnl),
+            stage
+        );
+        lines.push(match pnl {
+            pnl if pnl > 0.0 => Line::colored(text, Color::Green),
+            pnl if pnl < 0.0 => Line::colored(text, Color::Red),
+            _ => Line::plain(text),
+        });
+    }
+    lines
+}
+
+/// Account balances, IB's view of each position and any PnL that
+/// disagrees with ours by more than `tolerance`.
+pub fn account_lines(account: &Account, checks: &[PnlCheck], tolerance: f64) -> Vec<Line> {
+    let summary = &account.summary;
+    let money = |value: Option<f64>| value.map_or("-".to_string(), format_money);
+    let mut lines = vec![
+        Line::plain(format!(
+            "Account {}  Net liq {}  Cash {}",
+            summary.account,
+            money(summary.net_liquidation),
+            money(summary.cash)
+        )),
+        Line::plain(format!(
+            "Buying power {}  Maint margin {}  Unrealized {}",
+            money(summary.buying_power),
+            money(summary.maintenance_margin),
+            format_money(account.unrealized_pnl())
+        )),
+        Line::plain(format!(
+            "  {:<8} {:>8} {:>10} {:>12} {:>11}",
+            "Symbol", "Position", "Price", "Value", "Unrealized"
+        )),
+    ];
+    for position in &account.positions {
+        let text = format!(
+            "  {:<8} {:>8} {:>10.2} {:>12.2} {:>11}",
+            position.symbol,
+            position.position,
+            position.market_price,
+            position.market_value,
+            format_money(position.unrealized_pnl)
+        );
+        lines.push(match position.unrealized_pnl {
+            pnl if pnl > 0.0 => Line::colored(text, Color::Green),
+            pnl if pnl < 0.0 => Line::colored(text, Color::Red),
+            _ => Line::plain(text),
+        });
+    }
+    for check in checks.iter().filter(|check| check.is_mismatch(tolerance)) {
+        lines.push(Line::colored(
+            format!(
+                "{}: IB unrealized {} vs ours {}",
+                check.symbol,
+                format_money(check.ib_unrealized_pnl),
+                format_money(check.local_unrealized_pnl)
+            ),
+            Color::Yellow,
+        ));
+    }
+    lines
+}
+
+/// IB's daily, unrealized and realized PnL above ours. Empty until IB has
+/// sent its first update.
+pub fn pnl_lines(ib: Option<&PnlBreakdown>, local: &PnlBreakdown) -> Vec<Line> {
+    let Some(ib) = ib else {
+        return Vec::new();
+    };
+    let row = |source: &str, pnl: &PnlBreakdown| {
+        Line::plain(format!(
+            "  {:<6} {:>11} {:>11} {:>11}",
+            source,
+            format_money(pnl.daily),
+            format_money(pnl.unrealized),
+            format_money(pnl.realized)
+        ))
+    };
+    vec![
+        Line::plain(format!(
+            "  {:<6} {:>11} {:>11} {:>11}",
+            "PnL", "Daily", "Unrealized", "Realized"
+        )),
+        row("IB", ib),
+        row("Ours", local),
+    ]
+}
+
+/// Screen state that isn't part of the trades: the message log and an
+/// optional panel such as the account view.
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    messages: VecDeque<String>,
+    prompt: Option<String>,
+    pnl: Vec<Line>,
+    panel: Vec<Line>,
+}
+
+impl Dashboard {
+    pub fn new() -> Self {
+        Dashboard::default()
+    }
+
+    /// Adds a status message, dropping the oldest once the area is full.
+    pub fn message(&mut self, message: impl Into<String>) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(message.into());
+    }
+
+    /// Replaces the Buy/Sell prompt, e.g. with a question that has to be
+    /// answered first. `None` restores it.
+    pub fn set_prompt(&mut self, prompt: Option<String>) {
+        self.prompt = prompt;
+    }
+
+    /// IB's PnL next to ours, drawn below the session; see `pnl_lines`.
+    pub fn set_pnl(&mut self, pnl: Vec<Line>) {
+        self.pnl = pnl;
+    }
+
+    /// Lines drawn between the watchlist and the messages; empty hides
+    /// the panel.
+    pub fn set_panel(&mut self, panel: Vec<Line>) {
+        self.panel = panel;
+    }
+
+    pub fn messages(&self) -> impl Iterator<Item = &String> {
+        self.messages.iter()
+    }
+
+    /// Portfolio PnL, then price and prompt for the selected row, the
+    /// watchlist table, the selected row's session, the PnL comparison,
+    /// the panel and the messages.
+    pub fn frame(&self, rows: &[WatchRow], selected: usize, portfolio_pnl: f64) -> Vec<Line> {
+        let mut lines = vec![pnl_line(portfolio_pnl)];
+        let Some(row) = rows.get(selected) else {
+            return lines;
+        };
+        let trade = row.trade;
+        let mut price = price_line(&trade.symbol, trade.current_price);
+        if let Some(vwap) = row.vwap {
+            price.text.push_str(&format!("  VWAP ${:.2}", vwap));
+        }
+        if let Some(atr) = row.atr {
+            price.text.push_str(&format!("  ATR {:.2}", atr));
+        }
+        lines.push(price);
+        lines.push(match &self.prompt {
+            Some(prompt) => Line::colored(prompt.as_str(), Color::Yellow),
+            None => prompt_line(trade, row.order_working),
+        });
+        lines.push(Line::plain(""));
+        lines.extend(watchlist_lines(rows, selected));
+        lines.push(Line::plain(""));
+        if let Some(session) = trade.market_session() {
+            let color = if session.is_regular() {
+                Color::Reset
+            } else {
+                Color::Yellow
+            };
+            lines.push(Line::colored(format!("Session: {}", session), color));
+        }
+        if !self.pnl.is_empty() {
+            lines.extend(self.pnl.iter().cloned());
+            lines.push(Line::plain(""));
+        }
+        if !self.panel.is_empty() {
+            lines.extend(self.panel.iter().cloned());
+            lines.push(Line::plain(""));
+        }
+        lines.extend(self.messages.iter().map(Line::plain));
+        lines
+    }
+}
+
+/// Full-screen terminal in raw mode. Only rows that changed since the last
+/// frame are rewritten, so ticks don't flicker. The terminal is restored on
+/// drop and by a panic hook.
+pub struct Terminal {
+    stdout: Stdout,
+    previous: Vec<Line>,
+}
+
+impl Terminal {
+    pub fn enter() -> Result<Self> {
+        install_panic_hook();
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(
+            stdout,
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+        Ok(Terminal {
+            stdout,
+            previous: Vec::new(),
+        })
+    }
+
+    pub fn draw(&mut self, frame: &[Line]) -> Result<()> {
+        for (row, line) in frame.iter().enumerate() {
+            if self.previous.get(row) == Some(line) {
+                continue;
+            }
+            queue!(
+                self.stdout,
+                cursor::MoveTo(0, row as u16),
+                terminal::Clear(terminal::ClearType::CurrentLine),
+                SetForegroundColor(line.color.unwrap_or(Color::Reset)),
+                Print(&line.text),
+                ResetColor
+            )?;
+        }
+        for row in frame.len()..self.previous.len() {
+            queue!(
+                self.stdout,
+                cursor::MoveTo(0, row as u16),
+                terminal::Clear(terminal::ClearType::CurrentLine)
+            )?;
+        }
+        self.stdout.flush()?;
+        self.previous = frame.to_vec();
+        Ok(())
+    }
+
+    /// Forces the next draw to repaint every row, e.g. after a resize.
+    pub fn invalidate(&mut self) -> Result<()> {
+        self.previous.clear();
+        execute!(self.stdout, terminal::Clear(terminal::ClearType::All))?;
+        Ok(())
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Leaves raw mode and the alternate screen. Safe to call more than once.
+pub fn restore() {
+    let _ = execute!(
+        io::stdout(),
+        ResetColor,
+        cursor::Show,
+        terminal::LeaveAlternateScreen
+    );
+    let _ = terminal::disable_raw_mode();
+}
+
+fn install_panic_hook() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore();
+            previous(info);
+        }));
+    });
+}
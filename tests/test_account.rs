@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod account_tests {
+    use ibapi::accounts::{AccountPortfolioValue, AccountUpdate, AccountValue};
+    use ibapi::contracts::Contract;
+    use ibxrust::account::{self, Account, AccountSummary, FaMethod};
+    use ibxrust::trade::Trade;
+    use ibxrust::ui;
+
+    fn portfolio(
+        symbol: &str,
+        contract_id: i32,
+        position: f64,
+        price: f64,
+        pnl: f64,
+    ) -> AccountUpdate {
+        let mut contract = Contract::stock(symbol);
+        contract.contract_id = contract_id;
+        AccountUpdate::PortfolioValue(AccountPortfolioValue {
+            contract,
+            position,
+            market_price: price,
+            market_value: position * price,
+            unrealized_pnl: pnl,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_summary_tags() {
+        let mut summary = AccountSummary::default();
+        summary.apply("DU123", "NetLiquidation", "100250.50", "USD");
+        summary.apply("DU123", "TotalCashValue", "40000", "USD");
+        summary.apply("DU123", "BuyingPower", "not a number", "USD");
+        summary.apply("DU123", "Cushion", "0.9", "");
+
+        assert_eq!(summary.account, "DU123");
+        assert_eq!(summary.currency, "USD");
+        assert_eq!(summary.net_liquidation, Some(100_250.5));
+        assert_eq!(summary.cash, Some(40_000.0));
+        assert_eq!(summary.buying_power, None);
+        assert_eq!(summary.maintenance_margin, None);
+    }
+
+    #[test]
+    fn test_portfolio_updates() {
+        let mut account = Account::default();
+        account.apply_update(&AccountUpdate::AccountValue(AccountValue {
+            key: "MaintMarginReq".to_string(),
+            value: "2500".to_string(),
+            currency: "USD".to_string(),
+            account: Some("DU123".to_string()),
+        }));
+        account.apply_update(&portfolio("AAPL", 265598, 100.0, 160.0, 1000.0));
+        account.apply_update(&portfolio("MSFT", 272093, 10.0, 400.0, -50.0));
+        account.apply_update(&portfolio("AAPL", 265598, 100.0, 161.0, 1100.0));
+
+        assert_eq!(account.summary.maintenance_margin, Some(2500.0));
+        assert_eq!(account.positions.len(), 2);
+        assert_eq!(account.positions[0].market_price, 161.0);
+        assert_eq!(account.unrealized_pnl(), 1050.0);
+
+        account.apply_update(&portfolio("MSFT", 272093, 0.0, 400.0, 0.0));
+        assert_eq!(account.positions.len(), 1);
+        assert_eq!(account.positions[0].symbol, "AAPL");
+    }
+
+    #[test]
+    fn test_reconcile_unrealized_pnl() {
+        let mut account = Account::new("DU123");
+        account.apply_update(&portfolio("AAPL", 265598, 100.0, 160.0, 999.0));
+        account.apply_update(&portfolio("MSFT", 272093, 10.0, 400.0, 80.0));
+
+        let mut aapl = Trade::new("AAPL".to_string());
+        aapl.contract_id = 265598;
+        aapl.position = 100;
+        aapl.entry_price = 150.0;
+        // Stale local price; the check uses IB's market price
+        aapl.current_price = 155.0;
+        let mut msft = Trade::new("MSFT".to_string());
+        msft.position = 10;
+        msft.entry_price = 390.0;
+        let flat = Trade::new("NVDA".to_string());
+        let mut elsewhere = msft.clone();
+        elsewhere.account = Some("DU999".to_string());
+        msft.account = Some("DU123".to_string());
+
+        let checks = account::reconcile(&account, &[aapl, msft, flat, elsewhere]);
+        assert_eq!(checks.len(), 2);
+        assert_eq!(checks[0].local_unrealized_pnl, 1000.0);
+        assert_eq!(checks[0].difference, -1.0);
+        assert!(!checks[0].is_mismatch(1.0));
+        assert_eq!(checks[1].symbol, "MSFT");
+        assert_eq!(checks[1].difference, -20.0);
+        assert!(checks[1].is_mismatch(1.0));
+
+        let lines = ui::account_lines(&account, &checks, 1.0);
+        assert!(lines
+            .last()
+            .unwrap()
+            .text
+            .starts_with("MSFT: IB unrealized $80.00 vs ours $100.00"));
+    }
+
+    #[test]
+    fn test_select_account() {
+        let managed = vec!["DU111".to_string(), "DU222".to_string()];
+        assert_eq!(account::select_account(&managed, None).unwrap(), "DU111");
+        assert_eq!(
+            account::select_account(&managed, Some("DU222")).unwrap(),
+            "DU222"
+        );
+        assert!(account::select_account(&managed, Some("DU333")).is_err());
+        assert!(account::select_account(&[], None).is_err());
+    }
+
+    #[test]
+    fn test_trading_mode_must_match_account() {
+        assert!(account::check_trading_mode("DU111", true).is_ok());
+        assert!(account::check_trading_mode("U1234", false).is_ok());
+        // A live login with --paper, or a paper one with --live, is refused
+        let err = account::check_trading_mode("U1234", true).unwrap_err();
+        assert!(err.to_string().contains("U1234 is a live account"));
+        assert!(account::check_trading_mode("DU111", false).is_err());
+        assert!(account::check_trading_mode("DF222", false).is_err());
+    }
+
+    #[test]
+    fn test_fa_method_names() {
+        assert_eq!("netliq".parse::<FaMethod>().unwrap(), FaMethod::NetLiq);
+        assert_eq!(
+            "equal_quantity".parse::<FaMethod>().unwrap(),
+            FaMethod::EqualQuantity
+        );
+        assert_eq!(FaMethod::PctChange.to_string(), "PctChange");
+        assert!("pro_rata".parse::<FaMethod>().is_err());
+    }
+}
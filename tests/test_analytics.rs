@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod analytics_tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use ibxrust::analytics::{self, PerformanceReport, TradeStats};
+    use ibxrust::export::CompletedTrade;
+    use ibxrust::journal::TradeRecord;
+    use ibxrust::trade::Stage;
+
+    /// Completed trade closed on `closed` after a 30 minute hold, no commissions
+    fn completed(id: i64, symbol: &str, pnl: f64, closed: DateTime<Utc>) -> CompletedTrade {
+        CompletedTrade {
+            record: TradeRecord {
+                id,
+                symbol: symbol.to_string(),
+                contract_id: 0,
+                account: None,
+                strategy: None,
+                shares: 100,
+                entry_price: 100.0,
+                exit_price: Some(100.0 + pnl / 100.0),
+                stop_price: None,
+                realized_pnl: Some(pnl),
+                stage: Stage::Close,
+                opened_at: closed - Duration::minutes(30),
+                closed_at: Some(closed),
+            },
+            fills: Vec::new(),
+        }
+    }
+
+    fn day(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, day, 15, 0, 0).unwrap()
+    }
+
+    fn create_trades() -> Vec<CompletedTrade> {
+        vec![
+            completed(1, "AAPL", 300.0, day(4)),
+            completed(2, "AAPL", -100.0, day(4)),
+            completed(3, "MSFT", -200.0, day(5)),
+            completed(4, "MSFT", 400.0, day(6)),
+        ]
+    }
+
+    #[test]
+    fn test_trade_stats() {
+        let stats = TradeStats::compute(&create_trades());
+        assert_eq!(stats.trades, 4);
+        assert_eq!(stats.win_rate, 0.5);
+        assert_eq!(stats.average_win, 350.0);
+        assert_eq!(stats.average_loss, -150.0);
+        assert_eq!(stats.profit_factor, Some(700.0 / 300.0));
+        assert_eq!(stats.expectancy, 100.0);
+        assert_eq!(stats.average_hold_seconds, 1800.0);
+
+        assert_eq!(TradeStats::compute(&[]), TradeStats::default());
+    }
+
+    #[test]
+    fn test_drawdown_and_ratios() {
+        assert_eq!(
+            analytics::max_drawdown([300.0, -100.0, -200.0, 400.0]),
+            300.0
+        );
+        assert_eq!(analytics::max_drawdown([-50.0, 100.0]), 50.0);
+
+        assert_eq!(analytics::sharpe_ratio(&[100.0]), None);
+        assert_eq!(analytics::sharpe_ratio(&[100.0, 100.0]), None);
+        assert!(analytics::sharpe_ratio(&[200.0, -200.0, 400.0]).unwrap() > 0.0);
+        assert_eq!(analytics::sortino_ratio(&[100.0, 50.0]), None);
+    }
+
+    #[test]
+    fn test_performance_report() {
+        let report = PerformanceReport::compute(&create_trades());
+        assert_eq!(report.daily.len(), 3);
+        assert_eq!(report.daily[0].pnl, 200.0);
+        assert_eq!(report.max_drawdown, 300.0);
+        assert!(report.sharpe.is_some() && report.sortino.is_some());
+
+        assert_eq!(report.by_symbol.len(), 2);
+        assert_eq!(report.by_symbol[0].symbol, "AAPL");
+        assert_eq!(report.by_symbol[0].stats.total_pnl, 200.0);
+        assert_eq!(report.by_symbol[1].stats.total_pnl, 200.0);
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod bars_tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use ibxrust::bars::{BarBuilder, BarBus, BarEvent, BarInterval, BarSource};
+    use ibxrust::history::Bar;
+
+    fn at(minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 4, 14, minute, second)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_intervals() {
+        assert_eq!("1m".parse::<BarInterval>().unwrap(), BarInterval::Time(60));
+        assert_eq!("30S".parse::<BarInterval>().unwrap(), BarInterval::Time(30));
+        assert_eq!(
+            "vol:5000".parse::<BarInterval>().unwrap(),
+            BarInterval::Volume(5000.0)
+        );
+        assert_eq!(
+            "range:0.5".parse::<BarInterval>().unwrap(),
+            BarInterval::Range(0.5)
+        );
+        for invalid in ["0m", "5x", "m", "", "vol:-1", "range:abc", "5é"] {
+            assert!(invalid.parse::<BarInterval>().is_err(), "{}", invalid);
+        }
+        assert_eq!(BarInterval::Time(300).to_string(), "5m");
+        assert_eq!(BarInterval::Time(3600).to_string(), "1h");
+    }
+
+    #[test]
+    fn test_bar_sizes_must_be_finite_and_positive() {
+        for invalid in [
+            "vol:NaN", "vol:inf", "vol:0", "range:nan", "range:-inf", "range:0", "range:-0.5",
+        ] {
+            assert!(invalid.parse::<BarInterval>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_time_bars() {
+        let mut builder = BarBuilder::new(BarInterval::Time(60));
+        assert_eq!(builder.on_trade(at(30, 5), 100.0, 100.0), None);
+        assert_eq!(builder.on_trade(at(30, 20), 101.0, 300.0), None);
+        assert_eq!(builder.on_trade(at(30, 50), 99.5, 100.0), None);
+
+        let bar = builder.on_trade(at(31, 2), 100.5, 200.0).unwrap();
+        assert_eq!(bar.time, at(30, 0));
+        assert_eq!(
+            (bar.open, bar.high, bar.low, bar.close),
+            (100.0, 101.0, 99.5, 99.5)
+        );
+        assert_eq!(bar.volume, 500.0);
+        assert_eq!(bar.wap, 100.5);
+        assert_eq!(bar.count, 3);
+
+        // A quiet minute still closes the bar
+        assert_eq!(builder.close_due(at(31, 59)), None);
+        let bar = builder.close_due(at(32, 0)).unwrap();
+        assert_eq!(bar.time, at(31, 0));
+        assert_eq!(bar.close, 100.5);
+        assert!(builder.current().is_none());
+    }
+
+    #[test]
+    fn test_volume_bars() {
+        let mut builder = BarBuilder::new(BarInterval::Volume(1000.0));
+        assert_eq!(builder.on_trade(at(30, 0), 50.0, 600.0), None);
+        let bar = builder.on_trade(at(30, 1), 51.0, 500.0).unwrap();
+        assert_eq!(bar.time, at(30, 0));
+        assert_eq!(bar.volume, 1100.0);
+        assert_eq!(bar.close, 51.0);
+        assert!(builder.current().is_none());
+        // Volume bars don't close on time
+        builder.on_trade(at(30, 2), 52.0, 10.0);
+        assert_eq!(builder.close_due(at(45, 0)), None);
+    }
+
+    #[test]
+    fn test_range_bars() {
+        let mut builder = BarBuilder::new(BarInterval::Range(1.0));
+        assert_eq!(builder.on_trade(at(30, 0), 100.0, 10.0), None);
+        assert_eq!(builder.on_trade(at(30, 1), 100.75, 10.0), None);
+        assert_eq!(builder.on_trade(at(30, 2), 99.8, 10.0), None);
+
+        let bar = builder.on_trade(at(30, 3), 99.7, 10.0).unwrap();
+        assert_eq!((bar.high, bar.low, bar.close), (100.75, 99.8, 99.8));
+        assert_eq!(bar.count, 3);
+        let current = builder.current().unwrap();
+        assert_eq!((current.open, current.time), (99.7, at(30, 3)));
+    }
+
+    #[test]
+    fn test_bus_fans_out() {
+        let mut bus = BarBus::new();
+        let first = bus.subscribe();
+        let second = bus.subscribe();
+        let event = BarEvent {
+            symbol: "AAPL".to_string(),
+            source: BarSource::Ticks(BarInterval::Time(60)),
+            bar: Bar {
+                time: at(30, 0),
+                open: 1.0,
+                high: 1.0,
+                low: 1.0,
+                close: 1.0,
+                volume: 0.0,
+                wap: 1.0,
+                count: 1,
+            },
+        };
+
+        bus.publish(event.clone());
+        assert_eq!(first.try_recv().unwrap(), event);
+        assert_eq!(second.try_recv().unwrap(), event);
+
+        // A subscriber that hung up doesn't stop the others
+        drop(first);
+        bus.publish(event.clone());
+        assert_eq!(second.try_recv().unwrap(), event);
+    }
+}
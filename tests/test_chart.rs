@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod chart_tests {
+    use chrono::{TimeZone, Utc};
+    use crossterm::style::Color;
+    use ibxrust::bars::BarInterval;
+    use ibxrust::chart::{self, Level};
+    use ibxrust::history::Bar;
+    use ibxrust::trade::Trade;
+
+    fn bar(minute: u32, open: f64, high: f64, low: f64, close: f64) -> Bar {
+        Bar {
+            time: Utc.with_ymd_and_hms(2024, 3, 4, 14, minute, 0).unwrap(),
+            open,
+            high,
+            low,
+            close,
+            volume: 100.0,
+            wap: close,
+            count: 1,
+        }
+    }
+
+    #[test]
+    fn test_merge_keeps_latest_columns_full() {
+        let bars: Vec<Bar> = (0..5)
+            .map(|i| {
+                let price = 100.0 + i as f64;
+                bar(i, price, price + 0.5, price - 0.5, price + 0.25)
+            })
+            .collect();
+        assert_eq!(chart::merge(&bars, 10), bars);
+
+        let merged = chart::merge(&bars, 2);
+        assert_eq!(merged.len(), 2);
+        // 5 bars into 2 columns of 3: the oldest column gets the 2 left over
+        assert_eq!((merged[0].open, merged[0].close), (100.0, 101.25));
+        assert_eq!(merged[0].count, 2);
+        let latest = merged[1];
+        assert_eq!(latest.time, bars[2].time);
+        assert_eq!(
+            (latest.open, latest.high, latest.low, latest.close),
+            (102.0, 104.5, 101.5, 104.25)
+        );
+        assert_eq!(latest.volume, 300.0);
+        assert_eq!(latest.count, 3);
+        assert!(chart::merge(&[], 10).is_empty());
+    }
+
+    #[test]
+    fn test_render_candles_and_levels() {
+        let bars = [
+            bar(0, 100.0, 104.0, 100.0, 103.0),
+            bar(1, 103.0, 103.0, 101.0, 101.0),
+        ];
+        let levels = [Level {
+            label: "stop",
+            price: 100.0,
+        }];
+        let lines = chart::render(&bars, &levels, 5);
+        assert_eq!(lines.len(), 5);
+        // Rows are $1 apart, from 104 at the top down to 100
+        let columns: Vec<String> = lines
+            .iter()
+            .map(|line| {
+                line.text
+                    .split_once(['┤', '│'])
+                    .unwrap()
+                    .1
+                    .chars()
+                    .take(2)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(columns, ["│ ", "█▒", "█▒", "█▒", "█─"]);
+        assert!(lines[0].text.starts_with("    104.00 ┤"));
+        assert!(lines[4].text.ends_with(" stop 100.00"));
+        assert_eq!(lines[4].color, Some(Color::Red));
+        assert_eq!(lines[2].color, None);
+    }
+
+    #[test]
+    fn test_chart_overlays_trade_levels() {
+        let mut trade = Trade::new("AAPL".to_string());
+        assert_eq!(
+            chart::chart_lines(&trade, BarInterval::Time(60), &[], 30)[1].text,
+            "  No bars yet"
+        );
+
+        trade.current_price = 102.0;
+        trade.open_position(10, 101.0);
+        trade.stop_price = Some(98.0);
+        trade.target_price = Some(trade.atr_target(2.0, 2.0));
+        assert_eq!(trade.target_price, Some(105.0));
+
+        let bars: Vec<Bar> = (0..40)
+            .map(|i| bar(i, 101.0, 102.0, 100.0, 101.5))
+            .collect();
+        let lines = chart::chart_lines(&trade, BarInterval::Time(60), &bars, 30);
+        assert_eq!(lines[0].text, "Chart AAPL  1m x 30  (+/- zoom)");
+        assert_eq!(lines.len(), 1 + chart::HEIGHT);
+        let text: String = lines.iter().map(|line| line.text.clone()).collect();
+        for label in ["last 102.00", "entry 101.00", "stop 98.00", "target 105.00"] {
+            assert!(text.contains(label), "{}", label);
+        }
+        // Only the 30 newest bars are drawn, a column each
+        let candles = lines[1].text.split_once('┤').unwrap().1;
+        assert_eq!(candles.chars().take_while(|c| *c != ' ').count(), 30);
+    }
+}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod cli_tests {
+    use clap::Parser;
+    use ibxrust::cli::{Cli, Command};
+    use ibxrust::config::{Config, ConfigOverrides};
+
+    #[test]
+    fn test_symbols_without_subcommand_trade() {
+        let cli = Cli::try_parse_from(["ibxrust", "AAPL", "MSFT"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.trade.symbols, vec!["AAPL", "MSFT"]);
+
+        let cli = Cli::try_parse_from(["ibxrust", "trade", "AAPL"]).unwrap();
+        match cli.command {
+            Some(Command::Trade(args)) => assert_eq!(args.symbols, vec!["AAPL"]),
+            other => panic!("expected trade, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_global_flags_after_subcommand() {
+        let cli = Cli::try_parse_from([
+            "ibxrust",
+            "positions",
+            "--json",
+            "--host",
+            "10.0.0.5",
+            "--port",
+            "4002",
+            "--client-id",
+            "7",
+            "--live",
+        ])
+        .unwrap();
+        assert!(matches!(cli.command, Some(Command::Positions)));
+        assert!(cli.json());
+
+        let overrides = cli.overrides();
+        assert_eq!(overrides.tws_host.as_deref(), Some("10.0.0.5"));
+        assert_eq!(overrides.tws_port, Some(4002));
+        assert_eq!(overrides.client_id, Some(7));
+        assert_eq!(overrides.paper_trading, Some(false));
+    }
+
+    #[test]
+    fn test_paper_and_live_conflict() {
+        assert!(Cli::try_parse_from(["ibxrust", "orders", "--paper", "--live"]).is_err());
+    }
+
+    #[test]
+    fn test_overrides_apply_over_env() {
+        let mut config = Config::from_env().unwrap();
+        config.apply(&ConfigOverrides {
+            tws_port: Some(4001),
+            paper_trading: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(config.tws_port, 4001);
+        assert!(config.paper_trading);
+    }
+}
@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod depth_tests {
+    use crossterm::style::Color;
+    use ibapi::market_data::realtime::{MarketDepth, MarketDepthL2, MarketDepths};
+    use ibapi::orders::Action;
+    use ibxrust::depth::{self, DepthLevel, Operation, OrderBook, Side};
+
+    fn depth(position: i32, operation: i32, side: i32, price: f64, size: f64) -> MarketDepths {
+        MarketDepths::MarketDepth(MarketDepth {
+            position,
+            operation,
+            side,
+            price,
+            size,
+        })
+    }
+
+    fn level(price: f64, size: f64) -> DepthLevel {
+        DepthLevel {
+            price,
+            size,
+            market_maker: String::new(),
+        }
+    }
+
+    /// Bids of 200 at 10.00 and 300 at 9.99, asks of 100 at 10.02 and 400
+    /// at 10.03.
+    fn book() -> OrderBook {
+        let mut book = OrderBook::new();
+        book.apply(Side::Bid, 0, Operation::Insert, level(10.0, 200.0));
+        book.apply(Side::Bid, 1, Operation::Insert, level(9.99, 300.0));
+        book.apply(Side::Ask, 0, Operation::Insert, level(10.02, 100.0));
+        book.apply(Side::Ask, 1, Operation::Insert, level(10.03, 400.0));
+        book
+    }
+
+    #[test]
+    fn test_book_follows_insert_update_delete() {
+        let mut book = OrderBook::new();
+        // Side 1 is bid, 0 is ask; operations are insert, update, delete
+        book.on_depth(&depth(0, 0, 1, 10.00, 200.0));
+        book.on_depth(&depth(0, 0, 1, 10.01, 100.0));
+        book.on_depth(&depth(0, 0, 0, 10.03, 500.0));
+        assert_eq!(book.bids, [level(10.01, 100.0), level(10.00, 200.0)]);
+        assert_eq!(book.best_ask(), Some(10.03));
+
+        book.on_depth(&depth(1, 1, 1, 10.00, 250.0));
+        book.on_depth(&depth(0, 2, 1, 10.01, 0.0));
+        assert_eq!(book.bids, [level(10.00, 250.0)]);
+        assert!((book.spread().unwrap() - 0.03).abs() < 1e-9);
+
+        // Out of range rows don't panic: deletes are dropped, updates append
+        book.on_depth(&depth(5, 2, 0, 0.0, 0.0));
+        book.on_depth(&depth(3, 1, 0, 10.04, 50.0));
+        book.on_depth(&depth(-1, 0, 0, 10.05, 50.0));
+        book.on_depth(&depth(0, 7, 0, 10.05, 50.0));
+        assert_eq!(book.asks, [level(10.03, 500.0), level(10.04, 50.0)]);
+
+        book.on_depth(&MarketDepths::MarketDepthL2(MarketDepthL2 {
+            position: 0,
+            market_maker: "ARCA".to_string(),
+            operation: 1,
+            side: 0,
+            price: 10.02,
+            size: 300.0,
+            smart_depth: true,
+        }));
+        assert_eq!(book.asks[0].market_maker, "ARCA");
+        assert_eq!(book.visible_size(Side::Ask, 1), 300.0);
+        assert_eq!(book.visible_size(Side::Ask, 5), 350.0);
+    }
+
+    #[test]
+    fn test_liquidity_warning() {
+        let book = book();
+        // Buys take the asks: 100 on the top level, 500 on the top two
+        assert_eq!(depth::check_liquidity(&book, Action::Buy, 500, 2), None);
+        let warning = depth::check_liquidity(&book, Action::Buy, 501, 2).unwrap();
+        assert_eq!(warning.side, Side::Ask);
+        assert_eq!(warning.visible, 500.0);
+        assert_eq!(
+            warning.to_string(),
+            "501 shares is more than the 500 on the top 2 ask levels, spread $0.02"
+        );
+
+        let warning = depth::check_liquidity(&book, Action::Sell, 300, 1).unwrap();
+        assert_eq!(warning.side, Side::Bid);
+        assert_eq!(warning.visible, 200.0);
+
+        // No book, no judgement
+        assert_eq!(
+            depth::check_liquidity(&OrderBook::new(), Action::Buy, 1000, 5),
+            None
+        );
+    }
+
+    #[test]
+    fn test_depth_ladder() {
+        let empty = depth::depth_lines("AAPL", &OrderBook::new(), None);
+        assert_eq!(empty[0].text, "Depth AAPL  Spread -");
+        assert_eq!(empty[1].text, "  No depth yet");
+
+        let mut book = book();
+        book.apply(Side::Ask, 2, Operation::Insert, level(10.05, 50.0));
+        let warning = depth::check_liquidity(&book, Action::Buy, 1000, 3);
+        let lines = depth::depth_lines("AAPL", &book, warning.as_ref());
+        let text: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(
+            text[..5],
+            [
+                "Depth AAPL  Spread $0.02",
+                "      Size        Bid | Ask        Size    ",
+                "       200      10.00 | 10.02      100     ",
+                "       300       9.99 | 10.03      400     ",
+                "                      | 10.05      50      ",
+            ]
+        );
+        assert_eq!(
+            lines[5].text,
+            "  1000 shares is more than the 550 on the top 3 ask levels, spread $0.02"
+        );
+        assert_eq!(lines[5].color, Some(Color::Yellow));
+    }
+}
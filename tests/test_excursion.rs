@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod excursion_tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use ibxrust::excursion::Excursion;
+    use ibxrust::trade::Trade;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 4, 15, 0, 0).unwrap() + Duration::seconds(seconds)
+    }
+
+    /// Long 100 shares at $50 with a 60 second sample interval
+    fn create_held_trade() -> Trade {
+        let mut trade = Trade::new("AAPL".to_string());
+        trade.excursion = Excursion::new(60);
+        trade.open_position(100, 50.0);
+        trade
+    }
+
+    #[test]
+    fn test_tracks_favorable_and_adverse_extremes() {
+        let mut trade = create_held_trade();
+        trade.update_price_at(49.0, at(0));
+        trade.update_price_at(52.0, at(10));
+        trade.update_price_at(48.5, at(20));
+        trade.update_price_at(51.0, at(30));
+
+        let best = trade.excursion.max_favorable.unwrap();
+        assert_eq!(best.pnl, 200.0);
+        assert_eq!(best.price, 52.0);
+        assert_eq!(best.at, at(10));
+
+        let worst = trade.excursion.max_adverse.unwrap();
+        assert_eq!(worst.pnl, -150.0);
+        assert_eq!(worst.at, at(20));
+    }
+
+    #[test]
+    fn test_price_path_is_sampled_at_interval() {
+        let mut trade = create_held_trade();
+        for second in 0..=180 {
+            trade.update_price_at(50.0 + second as f64 / 100.0, at(second));
+        }
+        let times: Vec<_> = trade.excursion.path.iter().map(|point| point.at).collect();
+        assert_eq!(times, vec![at(0), at(60), at(120), at(180)]);
+    }
+
+    #[test]
+    fn test_only_tracked_while_holding() {
+        let mut trade = Trade::new("AAPL".to_string());
+        trade.update_price_at(50.0, at(0));
+        assert!(trade.excursion.path.is_empty());
+
+        let mut trade = create_held_trade();
+        trade.update_price_at(55.0, at(0));
+        trade.close_position();
+        trade.update_price_at(60.0, at(10));
+        assert_eq!(trade.excursion.mfe(), 500.0);
+        assert_eq!(trade.excursion.path.len(), 1);
+
+        // A new position starts from a clean slate
+        trade.open_position(100, 60.0);
+        assert_eq!(trade.excursion.max_favorable, None);
+        assert_eq!(trade.excursion.sample_interval_secs, 60);
+    }
+}
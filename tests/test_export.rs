@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod export_tests {
+    use chrono::Utc;
+    use ibxrust::export::{self, ExportColumn};
+    use ibxrust::journal::{Fill, Journal, TradeFilter};
+    use ibxrust::lots::LotMethod;
+    use ibxrust::trade::Trade;
+
+    fn fill(execution_id: &str, side: &str, price: f64, commission: f64) -> Fill {
+        Fill {
+            execution_id: execution_id.to_string(),
+            order_id: 1,
+            side: side.to_string(),
+            shares: 100.0,
+            price,
+            commission: Some(commission),
+            filled_at: Utc::now(),
+        }
+    }
+
+    /// Journal with one completed AAPL trade (+$250 gross, $2 commission) and one open MSFT trade
+    fn create_journal() -> Journal {
+        let journal = Journal::open_in_memory().unwrap();
+
+        let mut closed = Trade::new("AAPL".to_string());
+        closed.open_position(100, 50.0);
+        let id = journal.open_trade(&mut closed).unwrap();
+        journal
+            .record_fill(id, &fill("e1", "BOT", 50.0, 1.0))
+            .unwrap();
+        closed.update_price(52.5);
+        closed.close_position();
+        journal.close_trade(&closed).unwrap();
+        journal
+            .record_fill(id, &fill("e2", "SLD", 52.5, 1.0))
+            .unwrap();
+
+        let mut open = Trade::new("MSFT".to_string());
+        open.open_position(10, 400.0);
+        journal.open_trade(&mut open).unwrap();
+
+        journal
+    }
+
+    #[test]
+    fn test_only_completed_trades_are_exported() {
+        let journal = create_journal();
+        let trades =
+            export::completed_trades(&journal, &TradeFilter::default(), None, None).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].fills.len(), 2);
+        assert_eq!(trades[0].commission(), 2.0);
+        assert_eq!(trades[0].net_pnl(), 248.0);
+
+        let tomorrow = Utc::now().date_naive().succ_opt().unwrap();
+        let later =
+            export::completed_trades(&journal, &TradeFilter::default(), Some(tomorrow), None)
+                .unwrap();
+        assert!(later.is_empty());
+    }
+
+    #[test]
+    fn test_csv_with_selected_columns() {
+        let journal = create_journal();
+        let trades =
+            export::completed_trades(&journal, &TradeFilter::default(), None, None).unwrap();
+        let columns: Vec<ExportColumn> = ["symbol", "shares", "commission", "net_pnl"]
+            .iter()
+            .map(|name| name.parse().unwrap())
+            .collect();
+
+        let mut output = Vec::new();
+        export::write_csv(&mut output, &trades, &columns).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "symbol,shares,commission,net_pnl\nAAPL,100,2.00,248.00\n"
+        );
+
+        assert!("bogus".parse::<ExportColumn>().is_err());
+    }
+
+    #[test]
+    fn test_tax_lot_format() {
+        let journal = create_journal();
+        let trades =
+            export::completed_trades(&journal, &TradeFilter::default(), None, None).unwrap();
+
+        let tax_lots = export::tax_lots(&journal, &TradeFilter::default(), LotMethod::Fifo, None, None).unwrap();
+        assert_eq!(tax_lots.gains().short_term, 248.0);
+        assert!(tax_lots.wash_sales.is_empty());
+
+        let mut output = Vec::new();
+        export::write_tax_lots(&mut output, &trades, &tax_lots).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        let today = Utc::now().format("%m/%d/%Y").to_string();
+        assert_eq!(
+            lines[0],
+            "Description,Date Acquired,Date Sold,Proceeds,Cost Basis,Gain or Loss,Term,Wash Sale"
+        );
+        assert_eq!(
+            lines[1],
+            format!("100 sh AAPL,{},{},5249.00,5001.00,248.00,Short,", today, today)
+        );
+    }
+}
@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod headless_tests {
+    use ibapi::orders::Action;
+    use ibxrust::headless::{OrderRequest, Policy};
+    use ibxrust::Error;
+
+    fn buy(quantity: Option<i32>, limit_price: Option<f64>) -> OrderRequest {
+        OrderRequest {
+            symbol: "AAPL".to_string(),
+            action: Action::Buy,
+            quantity,
+            limit_price,
+            wait_fill: None,
+        }
+    }
+
+    fn assert_risk_error(result: ibxrust::Result<()>, reason: &str) {
+        match result {
+            Err(Error::Risk(message)) => {
+                assert!(message.contains(reason), "unexpected reason: {}", message)
+            }
+            other => panic!(
+                "expected risk error containing {:?}, got {:?}",
+                reason, other
+            ),
+        }
+    }
+
+    #[test]
+    fn test_default_policy_needs_confirmation() {
+        let policy = Policy::default();
+        assert_risk_error(policy.check(&buy(Some(10), None), 150.0, false), "--yes");
+        assert!(policy.check(&buy(Some(10), None), 150.0, true).is_ok());
+
+        let policy = Policy {
+            auto_confirm: true,
+            ..Default::default()
+        };
+        assert!(policy.check(&buy(Some(10), None), 150.0, false).is_ok());
+    }
+
+    #[test]
+    fn test_policy_limits() {
+        let policy = Policy {
+            auto_confirm: true,
+            symbols: vec!["MSFT".to_string()],
+            ..Default::default()
+        };
+        assert_risk_error(
+            policy.check(&buy(Some(10), None), 150.0, false),
+            "symbol list",
+        );
+
+        let policy = Policy {
+            auto_confirm: true,
+            max_quantity: Some(50),
+            max_notional: Some(5_000.0),
+            require_limit: true,
+            ..Default::default()
+        };
+        assert_risk_error(
+            policy.check(&buy(Some(10), None), 150.0, false),
+            "limit price",
+        );
+        assert_risk_error(
+            policy.check(&buy(Some(60), Some(10.0)), 10.0, false),
+            "policy maximum of 50",
+        );
+        assert_risk_error(
+            policy.check(&buy(Some(40), Some(150.0)), 150.0, false),
+            "notional",
+        );
+        assert!(policy.check(&buy(Some(30), Some(150.0)), 150.0, false).is_ok());
+    }
+
+    #[test]
+    fn test_policy_file() {
+        let dir = std::env::temp_dir().join(format!("ibxrust-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("policy.json");
+
+        assert_eq!(Policy::load(&path).unwrap(), Policy::default());
+
+        std::fs::write(&path, r#"{"auto_confirm": true, "symbols": ["AAPL"]}"#).unwrap();
+        let policy = Policy::load(&path).unwrap();
+        assert!(policy.auto_confirm);
+        assert_eq!(policy.symbols, vec!["AAPL".to_string()]);
+        assert_eq!(policy.max_quantity, None);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(matches!(Policy::load(&path), Err(Error::Config(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(Error::Order("rejected".to_string()).exit_code(), 2);
+        assert_eq!(Error::Risk("too big".to_string()).exit_code(), 3);
+        assert_eq!(Error::Connection("refused".to_string()).exit_code(), 4);
+        assert_eq!(
+            Error::TwsApi(ibapi::Error::ConnectionFailed).exit_code(),
+            4
+        );
+        assert_eq!(Error::Other("unknown".to_string()).exit_code(), 1);
+    }
+}
@@ -0,0 +1,155 @@
+#[cfg(test)]
+mod history_tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use clap::Parser;
+    use ibapi::market_data::historical::BarSize;
+    use ibxrust::cli::{Cli, Command};
+    use ibxrust::history::{self, Bar, BarCache, BarQuery, BarType, Pacer};
+    use std::time::{Duration, Instant};
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 4, hour, minute, 0).unwrap()
+    }
+
+    fn bar(time: DateTime<Utc>, close: f64) -> Bar {
+        Bar {
+            time,
+            open: close - 0.5,
+            high: close + 1.0,
+            low: close - 1.0,
+            close,
+            volume: 1000.0,
+            wap: close,
+            count: 12,
+        }
+    }
+
+    fn five_minute_trades() -> BarQuery {
+        BarQuery {
+            bar_size: BarSize::Min5,
+            bar_type: BarType::Trades,
+            use_rth: true,
+        }
+    }
+
+    #[test]
+    fn test_bar_sizes_and_types() {
+        assert_eq!(history::parse_bar_size("5m").unwrap(), BarSize::Min5);
+        assert_eq!(history::parse_bar_size("1S").unwrap(), BarSize::Sec);
+        assert_eq!(history::parse_bar_size("1d").unwrap(), BarSize::Day);
+        assert!(history::parse_bar_size("1w").is_err());
+
+        assert_eq!("bid-ask".parse::<BarType>().unwrap(), BarType::BidAsk);
+        assert_eq!(BarType::Midpoint.to_string(), "MIDPOINT");
+        assert!("ticks".parse::<BarType>().is_err());
+        assert_eq!(five_minute_trades().series(), "5 mins/TRADES/rth");
+    }
+
+    #[test]
+    fn test_chunks_align_to_span() {
+        // 1 second bars come 30 minutes per request
+        let windows = history::chunks(BarSize::Sec, at(14, 10), at(15, 5));
+        assert_eq!(
+            windows,
+            vec![
+                (at(14, 0), at(14, 30)),
+                (at(14, 30), at(15, 0)),
+                (at(15, 0), at(15, 30)),
+            ]
+        );
+        // A later overlapping request reuses the same windows
+        assert_eq!(
+            history::chunks(BarSize::Sec, at(14, 45), at(15, 0)),
+            vec![(at(14, 30), at(15, 0))]
+        );
+        assert!(history::chunks(BarSize::Day, at(15, 0), at(15, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_pacer_limits() {
+        let start = Instant::now();
+        let mut pacer = Pacer::new();
+        for _ in 0..5 {
+            assert_eq!(pacer.delay_at(start), Duration::ZERO);
+            pacer.record(start);
+        }
+        // The sixth request within two seconds waits
+        assert_eq!(pacer.delay_at(start), Duration::from_secs(2));
+
+        let mut pacer = Pacer::new();
+        for second in 0..60 {
+            pacer.record(start + Duration::from_secs(second * 3));
+        }
+        let now = start + Duration::from_secs(180);
+        assert_eq!(pacer.delay_at(now), Duration::from_secs(420));
+        assert_eq!(
+            pacer.delay_at(start + Duration::from_secs(600)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let mut cache = BarCache::open_in_memory().unwrap();
+        let query = five_minute_trades();
+        let bars = [bar(at(14, 35), 101.0), bar(at(14, 30), 100.0)];
+        let window = at(0, 0);
+
+        cache.store(265598, &query, &bars, None).unwrap();
+        assert!(!cache.has_chunk(265598, &query, window).unwrap());
+        cache
+            .store(265598, &query, &[bar(at(14, 35), 102.0)], Some(window))
+            .unwrap();
+        assert!(cache.has_chunk(265598, &query, window).unwrap());
+
+        let midpoint = BarQuery {
+            bar_type: BarType::Midpoint,
+            ..query
+        };
+        assert!(!cache.has_chunk(265598, &midpoint, window).unwrap());
+
+        let cached = cache.bars(265598, &query, at(14, 0), at(15, 0)).unwrap();
+        assert_eq!(cached.len(), 2);
+        assert_eq!(cached[0], bars[1]);
+        assert_eq!(cached[1].close, 102.0);
+        assert!(cache
+            .bars(265598, &query, at(14, 0), at(14, 30))
+            .unwrap()
+            .is_empty());
+        assert!(cache
+            .bars(272093, &query, at(14, 0), at(15, 0))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut out = Vec::new();
+        history::write_csv(&mut out, &[bar(at(14, 30), 100.0)]).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "time,open,high,low,close,volume,wap,count");
+        assert_eq!(
+            lines[1],
+            "2024-03-04T14:30:00+00:00,99.5,101,99,100,1000,100,12"
+        );
+    }
+
+    #[test]
+    fn test_bars_command() {
+        let cli = Cli::try_parse_from([
+            "ibxrust", "bars", "AAPL", "--size", "5m", "--type", "midpoint", "--days", "3",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Command::Bars(args)) => {
+                assert_eq!(args.size, BarSize::Min5);
+                assert_eq!(args.bar_type, BarType::Midpoint);
+                assert_eq!(args.days, 3);
+                assert!(!args.outside_rth);
+            }
+            other => panic!("expected bars, got {:?}", other),
+        }
+        assert!(Cli::try_parse_from(["ibxrust", "bars", "AAPL", "--size", "7m"]).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod indicators_tests {
+    use chrono::{TimeZone, Utc};
+    use ibxrust::history::Bar;
+    use ibxrust::indicators::{Atr, Bollinger, Ema, Indicator, Macd, Rsi, Sma, Vwap};
+    use ibxrust::trade::Trade;
+
+    fn close(value: f64) -> Bar {
+        ohlc(value, value, value, value)
+    }
+
+    fn ohlc(open: f64, high: f64, low: f64, close: f64) -> Bar {
+        Bar {
+            time: Utc.with_ymd_and_hms(2024, 3, 4, 14, 30, 0).unwrap(),
+            open,
+            high,
+            low,
+            close,
+            volume: 100.0,
+            wap: close,
+            count: 1,
+        }
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("indicator has no value yet");
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_sma_and_ema() {
+        let mut sma = Sma::new(3);
+        assert_eq!(sma.update(1.0), None);
+        assert_eq!(sma.update(2.0), None);
+        assert_close(sma.update(3.0), 2.0);
+        assert_close(sma.update(7.0), 4.0);
+
+        // Seeded with the SMA of the first three, then alpha = 0.5
+        let mut ema = Ema::new(3);
+        for value in [1.0, 2.0] {
+            assert_eq!(ema.update(value), None);
+        }
+        assert_close(ema.update(3.0), 2.0);
+        assert_close(ema.on_bar(&close(6.0)), 4.0);
+        assert_close(ema.value(), 4.0);
+    }
+
+    #[test]
+    fn test_rsi() {
+        let mut rsi = Rsi::new(2);
+        assert_eq!(rsi.update(10.0), None);
+        assert_eq!(rsi.update(11.0), None);
+        // Average gain 0.5, average loss 0.5
+        assert_close(rsi.update(10.0), 50.0);
+        // Wilder: gain (0.5 + 2) / 2, loss (0.5 + 0) / 2
+        assert_close(rsi.update(12.0), 100.0 - 100.0 / (1.0 + 1.25 / 0.25));
+
+        let mut flat = Rsi::new(2);
+        for _ in 0..3 {
+            flat.update(5.0);
+        }
+        assert_close(flat.value(), 50.0);
+    }
+
+    #[test]
+    fn test_atr_uses_previous_close() {
+        let mut atr = Atr::new(2);
+        assert_eq!(atr.on_bar(&ohlc(10.0, 11.0, 9.0, 10.0)), None);
+        // Gap up: true range from the previous close 10 to the high 14
+        assert_close(atr.on_bar(&ohlc(13.0, 14.0, 12.5, 13.5)), 3.0);
+        assert_close(atr.on_bar(&ohlc(13.5, 14.0, 13.0, 13.5)), 2.0);
+
+        let mut trade = Trade::new("AAPL".to_string());
+        trade.current_price = 100.0;
+        assert_eq!(trade.atr_stop(2.0, 1.5), 97.0);
+        trade.open_position(10, 101.0);
+        assert_eq!(trade.atr_stop(2.0, 1.5), 98.0);
+        trade.position = -10;
+        assert_eq!(trade.atr_stop(2.0, 1.5), 104.0);
+    }
+
+    #[test]
+    fn test_vwap() {
+        let mut vwap = Vwap::new();
+        assert_eq!(vwap.update(100.0, 0.0), None);
+        vwap.update(100.0, 100.0);
+        assert_close(vwap.update(103.0, 200.0), 102.0);
+
+        vwap.reset();
+        assert_eq!(vwap.value(), None);
+        // Typical price (12 + 9 + 10.5) / 3 at 100 shares
+        assert_close(vwap.on_bar(&ohlc(10.0, 12.0, 9.0, 10.5)), 10.5);
+    }
+
+    #[test]
+    fn test_bollinger() {
+        let mut bands = Bollinger::new(4, 2.0);
+        for value in [2.0, 4.0, 4.0] {
+            assert_eq!(bands.update(value), None);
+        }
+        // Mean 4, population deviation 1.414...
+        let value = bands.on_bar(&close(6.0)).unwrap();
+        assert_close(Some(value.middle), 4.0);
+        assert_close(Some(value.upper), 4.0 + 2.0 * 2f64.sqrt());
+        assert_close(Some(value.lower), 4.0 - 2.0 * 2f64.sqrt());
+    }
+
+    #[test]
+    fn test_macd() {
+        let mut macd = Macd::new(2, 3, 2);
+        let values = [1.0, 2.0, 3.0, 4.0];
+        let outputs: Vec<_> = values.iter().map(|value| macd.update(*value)).collect();
+        // MACD needs the slow EMA (3) and then two MACD values for the signal
+        assert_eq!(outputs[..3], [None, None, None]);
+        let value = outputs[3].unwrap();
+        // Fast EMA: 1.5, 2.5, 3.5; slow EMA: 2, 3; MACD: 0.5, 0.5
+        assert_close(Some(value.macd), 0.5);
+        assert_close(Some(value.signal), 0.5);
+        assert_close(Some(value.histogram), 0.0);
+        assert_eq!(macd.value(), Some(value));
+    }
+}
@@ -0,0 +1,220 @@
+#[cfg(test)]
+mod journal_tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use ibapi::orders::{order_builder, Action, CommissionReport, ExecutionData};
+    use ibxrust::journal::{self, Fill, Journal, TradeFilter};
+    use ibxrust::trade::{Stage, Trade};
+
+    /// Open and close a trade, journaling each step
+    fn journal_round_trip(journal: &Journal, symbol: &str, strategy: &str, exit: f64) -> Trade {
+        let mut trade = Trade::new(symbol.to_string());
+        trade.strategy = Some(strategy.to_string());
+        trade.open_position(100, 50.0);
+        journal.open_trade(&mut trade).unwrap();
+
+        trade.update_price(exit);
+        trade.close_position();
+        journal.close_trade(&trade).unwrap();
+        trade
+    }
+
+    #[test]
+    fn test_migrations_are_applied_once() {
+        let journal = Journal::open_in_memory().unwrap();
+        assert_eq!(journal.schema_version().unwrap(), 4);
+    }
+
+    #[test]
+    fn test_trade_lifecycle_is_persisted() {
+        let journal = Journal::open_in_memory().unwrap();
+        let trade = journal_round_trip(&journal, "AAPL", "breakout", 52.5);
+        let id = trade.journal_id.unwrap();
+
+        let record = journal.trade(id).unwrap().unwrap();
+        assert_eq!(record.symbol, "AAPL");
+        assert_eq!(record.shares, 100);
+        assert_eq!(record.entry_price, 50.0);
+        assert_eq!(record.exit_price, Some(52.5));
+        assert_eq!(record.realized_pnl, Some(250.0));
+        assert_eq!(record.stage, Stage::Close);
+        assert!(record.closed_at.is_some());
+
+        let stages: Vec<Stage> = journal.stage_history(id).unwrap().iter().map(|t| t.stage).collect();
+        assert_eq!(stages, vec![Stage::Hold, Stage::Close]);
+    }
+
+    #[test]
+    fn test_orders_and_fills() {
+        let journal = Journal::open_in_memory().unwrap();
+        let trade = journal_round_trip(&journal, "MSFT", "scalp", 49.0);
+        let id = trade.journal_id.unwrap();
+
+        journal
+            .record_order(id, 7, &order_builder::limit_order(Action::Buy, 100.0, 50.0))
+            .unwrap();
+        journal.update_order_status(7, "Filled").unwrap();
+        journal
+            .record_fill(
+                id,
+                &Fill {
+                    execution_id: "0001.01".to_string(),
+                    order_id: 7,
+                    side: "BOT".to_string(),
+                    shares: 100.0,
+                    price: 50.0,
+                    commission: None,
+                    filled_at: Utc::now(),
+                },
+            )
+            .unwrap();
+        journal
+            .record_commission(&CommissionReport {
+                execution_id: "0001.01".to_string(),
+                commission: 1.25,
+                currency: "USD".to_string(),
+                realized_pnl: None,
+                yields: None,
+                yield_redemption_date: String::new(),
+            })
+            .unwrap();
+
+        let fills = journal.fills(id).unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].commission, Some(1.25));
+    }
+
+    #[test]
+    fn test_query_trades() {
+        let journal = Journal::open_in_memory().unwrap();
+        journal_round_trip(&journal, "AAPL", "breakout", 51.0);
+        journal_round_trip(&journal, "MSFT", "breakout", 49.0);
+        journal_round_trip(&journal, "AAPL", "scalp", 50.5);
+
+        assert_eq!(journal.trades_by_symbol("AAPL").unwrap().len(), 2);
+        assert_eq!(journal.trades_by_strategy("breakout").unwrap().len(), 2);
+
+        let filter = TradeFilter {
+            symbol: Some("AAPL".to_string()),
+            strategy: Some("scalp".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(journal.query_trades(&filter).unwrap().len(), 1);
+
+        let now = Utc::now();
+        assert_eq!(journal.trades_between(now - Duration::hours(1), now + Duration::hours(1)).unwrap().len(), 3);
+        assert!(journal.trades_between(now + Duration::hours(1), now + Duration::hours(2)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trades_are_scoped_by_account() {
+        let journal = Journal::open_in_memory().unwrap();
+        for account in ["DU111111", "DU222222"] {
+            let mut trade = Trade::new("AAPL".to_string());
+            trade.account = Some(account.to_string());
+            trade.open_position(100, 50.0);
+            journal.open_trade(&mut trade).unwrap();
+        }
+        journal_round_trip(&journal, "AAPL", "breakout", 51.0);
+
+        let filter = TradeFilter {
+            account: Some("DU222222".to_string()),
+            ..Default::default()
+        };
+        let records = journal.query_trades(&filter).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].account.as_deref(), Some("DU222222"));
+        assert_eq!(journal.trades_by_symbol("AAPL").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_reopening_database_keeps_trades() {
+        let path = std::env::temp_dir().join(format!("ibxrust-journal-{}.db", std::process::id()));
+        {
+            let journal = Journal::open(&path).unwrap();
+            journal_round_trip(&journal, "AAPL", "breakout", 51.0);
+        }
+        let journal = Journal::open(&path).unwrap();
+        assert_eq!(journal.schema_version().unwrap(), 4);
+        assert_eq!(journal.trades_by_symbol("AAPL").unwrap().len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_excursion_is_stored_with_trade() {
+        let journal = Journal::open_in_memory().unwrap();
+        let mut trade = Trade::new("AAPL".to_string());
+        trade.open_position(100, 50.0);
+        journal.open_trade(&mut trade).unwrap();
+        trade.update_price(48.0);
+        trade.update_price(53.0);
+        trade.close_position();
+        journal.close_trade(&trade).unwrap();
+
+        let excursion = journal
+            .excursion(trade.journal_id.unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(excursion.mfe(), 300.0);
+        assert_eq!(excursion.mae(), -200.0);
+        assert_eq!(excursion, trade.excursion);
+    }
+
+    #[test]
+    fn test_partial_exit_is_persisted() {
+        let journal = Journal::open_in_memory().unwrap();
+        let mut trade = Trade::new("AAPL".to_string());
+        trade.open_position(100, 50.0);
+        journal.open_trade(&mut trade).unwrap();
+
+        trade.update_price(52.0);
+        assert_eq!(trade.reduce_position(40), 80.0);
+        journal.update_position(&trade).unwrap();
+        let record = journal.trade(trade.journal_id.unwrap()).unwrap().unwrap();
+        assert_eq!(record.shares, 60);
+        assert_eq!(record.realized_pnl, Some(80.0));
+        assert_eq!(record.stage, Stage::Hold);
+
+        trade.update_price(53.0);
+        trade.close_position();
+        journal.close_trade(&trade).unwrap();
+        let record = journal.trade(trade.journal_id.unwrap()).unwrap().unwrap();
+        assert_eq!(record.realized_pnl, Some(260.0));
+    }
+
+    #[test]
+    fn test_repeated_execution_keeps_time_and_commission() {
+        let journal = Journal::open_in_memory().unwrap();
+        let trade = journal_round_trip(&journal, "NVDA", "scalp", 51.0);
+        let id = trade.journal_id.unwrap();
+
+        let mut data = ExecutionData::default();
+        data.execution.execution_id = "0002.01".to_string();
+        data.execution.order_id = 9;
+        data.execution.side = "BOT".to_string();
+        data.execution.shares = 100.0;
+        data.execution.price = 50.0;
+        data.execution.time = "20240304  09:45:00 US/Eastern".to_string();
+        let fill = Fill::from_execution(&data);
+        assert_eq!(fill.filled_at, Utc.with_ymd_and_hms(2024, 3, 4, 14, 45, 0).unwrap());
+        assert_eq!(journal::parse_execution_time("not a time"), None);
+
+        journal.record_fill(id, &fill).unwrap();
+        journal
+            .record_commission(&CommissionReport {
+                execution_id: "0002.01".to_string(),
+                commission: 1.0,
+                currency: "USD".to_string(),
+                realized_pnl: None,
+                yields: None,
+                yield_redemption_date: String::new(),
+            })
+            .unwrap();
+        // Replayed on reconnect, without the commission
+        journal.record_fill(id, &fill).unwrap();
+
+        let fills = journal.fills(id).unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].commission, Some(1.0));
+        assert_eq!(fills[0].filled_at, fill.filled_at);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod kill_switch_tests {
+    use chrono::NaiveDate;
+    use ibapi::accounts::PnL;
+    use ibxrust::kill_switch::{session_pnl, KillSwitch};
+    use ibxrust::trade::Trade;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, d).unwrap()
+    }
+
+    /// A closed AAPL trade (-$300 realized) and an open MSFT trade (-$150 unrealized)
+    fn create_session_trades() -> Vec<Trade> {
+        let mut closed = Trade::new("AAPL".to_string());
+        closed.open_position(100, 150.0);
+        closed.update_price(147.0);
+        closed.close_position();
+
+        let mut open = Trade::new("MSFT".to_string());
+        open.open_position(50, 400.0);
+        open.update_price(397.0);
+
+        vec![closed, open]
+    }
+
+    #[test]
+    fn test_session_pnl_includes_realized_and_unrealized() {
+        let trades = create_session_trades();
+        assert_eq!(session_pnl(&trades), -450.0);
+    }
+
+    #[test]
+    fn test_trips_once_and_locks_until_next_session() {
+        let mut switch = KillSwitch::new(Some(400.0), true);
+        assert_eq!(switch.evaluate(-399.0, day(4)), None);
+        assert!(!switch.is_locked(day(4)));
+
+        assert_eq!(switch.evaluate(-450.0, day(4)), Some(-450.0));
+        assert!(switch.is_locked(day(4)));
+        assert_eq!(switch.evaluate(-500.0, day(4)), None);
+
+        assert!(!switch.is_locked(day(5)));
+    }
+
+    #[test]
+    fn test_account_daily_pnl_can_trip_switch() {
+        let mut switch = KillSwitch::new(Some(1000.0), false);
+        switch.update_account_pnl(&PnL {
+            daily_pnl: -1200.0,
+            unrealized_pnl: None,
+            realized_pnl: None,
+        });
+        assert_eq!(switch.evaluate(-100.0, day(4)), Some(-1200.0));
+    }
+
+    #[test]
+    fn test_disabled_without_limit() {
+        let mut switch = KillSwitch::new(None, false);
+        assert_eq!(switch.evaluate(-1_000_000.0, day(4)), None);
+        assert!(!switch.is_locked(day(4)));
+    }
+
+    #[test]
+    fn test_restarted_switch_stays_locked() {
+        let path = std::env::temp_dir().join(format!("ibxrust-kill-switch-{}.state", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut switch = KillSwitch::new(Some(400.0), false).with_state_file(&path).unwrap();
+        assert!(!switch.is_locked(day(4)));
+        assert_eq!(switch.evaluate(-450.0, day(4)), Some(-450.0));
+
+        let mut restarted = KillSwitch::new(Some(400.0), false).with_state_file(&path).unwrap();
+        assert!(restarted.is_locked(day(4)));
+        assert_eq!(restarted.evaluate(-500.0, day(4)), None);
+        assert!(!restarted.is_locked(day(5)));
+
+        std::fs::write(&path, "garbage").unwrap();
+        assert!(KillSwitch::new(Some(400.0), false).with_state_file(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
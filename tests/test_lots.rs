@@ -0,0 +1,141 @@
+#[cfg(test)]
+mod lots_tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use ibxrust::journal::{Fill, Journal, TradeFilter};
+    use ibxrust::lots::{LotLedger, LotMethod, Term};
+
+    fn day(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 15, 0, 0).unwrap()
+    }
+
+    /// Three AAPL lots: 100 @ $100, 100 @ $120, 100 @ $110
+    fn create_ledger(method: LotMethod) -> LotLedger {
+        let mut ledger = LotLedger::new(method);
+        ledger.buy("AAPL", 100.0, 100.0, day(2023, 1, 10));
+        ledger.buy("AAPL", 100.0, 120.0, day(2023, 6, 1));
+        ledger.buy("AAPL", 100.0, 110.0, day(2024, 2, 1));
+        ledger
+    }
+
+    #[test]
+    fn test_matching_methods() {
+        let sold_at = day(2024, 3, 1);
+
+        let mut fifo = create_ledger(LotMethod::Fifo);
+        let matched = fifo.sell("AAPL", 150.0, 115.0, sold_at, None).unwrap();
+        assert_eq!(
+            matched.iter().map(|lot| lot.lot_id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(matched[1].shares, 50.0);
+        assert_eq!(fifo.open_lots("AAPL").len(), 2);
+
+        let mut lifo = create_ledger(LotMethod::Lifo);
+        let matched = lifo.sell("AAPL", 100.0, 115.0, sold_at, None).unwrap();
+        assert_eq!(matched[0].lot_id, 3);
+
+        let mut highest = create_ledger(LotMethod::HighestCost);
+        let matched = highest.sell("AAPL", 100.0, 115.0, sold_at, None).unwrap();
+        assert_eq!(matched[0].lot_id, 2);
+        assert_eq!(matched[0].gain(), -500.0);
+    }
+
+    #[test]
+    fn test_specific_lot_selection() {
+        let mut ledger = create_ledger(LotMethod::SpecificLot);
+        assert!(ledger
+            .sell("AAPL", 100.0, 115.0, day(2024, 3, 1), None)
+            .is_err());
+
+        let matched = ledger
+            .sell("AAPL", 100.0, 115.0, day(2024, 3, 1), Some(&[3]))
+            .unwrap();
+        assert_eq!(matched[0].lot_id, 3);
+        assert!(ledger
+            .sell("AAPL", 100.0, 115.0, day(2024, 3, 1), Some(&[3]))
+            .is_err());
+        assert!(ledger
+            .sell("AAPL", 500.0, 115.0, day(2024, 3, 1), None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_refused_sale_leaves_ledger_unchanged() {
+        let mut ledger = create_ledger(LotMethod::SpecificLot);
+        // Lot 1 alone can't cover 150 shares
+        assert!(ledger.sell("AAPL", 150.0, 115.0, day(2024, 3, 1), Some(&[1])).is_err());
+        assert!(ledger.sell("AAPL", 150.0, 115.0, day(2024, 3, 1), Some(&[1, 1])).is_err());
+        assert!(ledger.closed_lots().is_empty());
+        let open: Vec<f64> = ledger.open_lots("AAPL").iter().map(|lot| lot.shares).collect();
+        assert_eq!(open, vec![100.0, 100.0, 100.0]);
+    }
+
+    #[test]
+    fn test_journal_replay_needs_lot_free_method() {
+        let journal = Journal::open_in_memory().unwrap();
+        assert!(LotLedger::from_journal(&journal, &TradeFilter::default(), LotMethod::SpecificLot).is_err());
+        assert!(LotLedger::from_journal(&journal, &TradeFilter::default(), LotMethod::Fifo).is_ok());
+    }
+
+    #[test]
+    fn test_short_and_long_term_gains() {
+        let mut ledger = create_ledger(LotMethod::Fifo);
+        ledger
+            .sell("AAPL", 200.0, 130.0, day(2024, 3, 1), None)
+            .unwrap();
+
+        let closed = ledger.closed_lots();
+        assert_eq!(closed[0].term, Term::LongTerm);
+        assert_eq!(closed[1].term, Term::ShortTerm);
+
+        let gains = ledger.realized_gains();
+        assert_eq!(gains.long_term, 3000.0);
+        assert_eq!(gains.short_term, 1000.0);
+        assert_eq!(gains.total(), 4000.0);
+
+        // Exactly one year is still short-term
+        assert_eq!(Term::of(day(2023, 3, 1), day(2024, 3, 1)), Term::ShortTerm);
+        assert_eq!(Term::of(day(2023, 3, 1), day(2024, 3, 2)), Term::LongTerm);
+    }
+
+    #[test]
+    fn test_wash_sale_candidates() {
+        let mut ledger = LotLedger::new(LotMethod::Fifo);
+        ledger.buy("MSFT", 10.0, 400.0, day(2024, 1, 2));
+        ledger
+            .sell("MSFT", 10.0, 380.0, day(2024, 2, 1), None)
+            .unwrap();
+        ledger.buy("MSFT", 10.0, 385.0, day(2024, 2, 1) + Duration::days(20));
+        ledger.buy("NVDA", 10.0, 700.0, day(2024, 2, 5));
+
+        let candidates = ledger.wash_sale_candidates();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].sold_lot_id, 1);
+        assert_eq!(candidates[0].replacement_lot_id, 2);
+        assert_eq!(candidates[0].loss, -200.0);
+    }
+
+    #[test]
+    fn test_fills_include_commission() {
+        let mut ledger = LotLedger::new(LotMethod::Fifo);
+        let fill = |side: &str, price: f64, at| Fill {
+            execution_id: format!("{}-{}", side, price),
+            order_id: 1,
+            side: side.to_string(),
+            shares: 100.0,
+            price,
+            commission: Some(1.0),
+            filled_at: at,
+        };
+        ledger
+            .apply_fill("AAPL", &fill("BOT", 50.0, day(2024, 1, 2)))
+            .unwrap();
+        ledger
+            .apply_fill("AAPL", &fill("SLD", 52.0, day(2024, 1, 3)))
+            .unwrap();
+
+        let closed = &ledger.closed_lots()[0];
+        assert_eq!(closed.cost_basis, 5001.0);
+        assert_eq!(closed.proceeds, 5199.0);
+    }
+}
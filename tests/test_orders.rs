@@ -0,0 +1,26 @@
+#[cfg(test)]
+mod orders_tests {
+    use ibapi::orders::Action;
+    use ibxrust::orders;
+    use ibxrust::trade::Trade;
+
+    #[test]
+    fn test_filled_entry_gets_stop_order() {
+        let mut trade = Trade::new("AAPL".to_string());
+        trade.stop_price = Some(48.0);
+        // Nothing to protect until the entry fills
+        assert!(orders::stop_order(&trade, 48.0).is_none());
+
+        trade.open_position(100, 50.0);
+        let order = orders::stop_order(&trade, 48.0).unwrap();
+        assert_eq!(order.order_type, "STP");
+        assert_eq!(order.action, Action::Sell);
+        assert_eq!(order.total_quantity, 100.0);
+        assert_eq!(order.aux_price, Some(48.0));
+
+        trade.position = -50;
+        let order = orders::stop_order(&trade, 52.0).unwrap();
+        assert_eq!(order.action, Action::Buy);
+        assert_eq!(order.total_quantity, 50.0);
+    }
+}
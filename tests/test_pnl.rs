@@ -0,0 +1,167 @@
+#[cfg(test)]
+mod pnl_tests {
+    use ibapi::accounts::{PnL, PnLSingle};
+    use ibxrust::journal::Fill;
+    use ibxrust::pnl::{self, Commissions, Discrepancy, PnlBreakdown, PositionPnl};
+    use ibxrust::trade::{Stage, Trade};
+    use ibxrust::ui;
+    use std::collections::HashMap;
+
+    fn held(symbol: &str, contract_id: i64, position: i32, entry: f64, price: f64) -> Trade {
+        let mut trade = Trade::new(symbol.to_string());
+        trade.contract_id = contract_id;
+        trade.stage = Stage::Hold;
+        trade.position = position;
+        trade.entry_price = entry;
+        trade.current_price = price;
+        trade
+    }
+
+    fn ib_position(position: f64, unrealized: f64) -> PositionPnl {
+        PositionPnl {
+            position,
+            pnl: PnlBreakdown::from_position(&PnLSingle {
+                position,
+                unrealized_pnl: unrealized,
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_breakdowns() {
+        let ib = PnlBreakdown::from_account(&PnL {
+            daily_pnl: 120.0,
+            unrealized_pnl: Some(100.0),
+            realized_pnl: None,
+        });
+        assert_eq!(ib.unrealized, 100.0);
+        assert_eq!(ib.realized, 0.0);
+
+        let mut closed = Trade::new("MSFT".to_string());
+        closed.stage = Stage::Close;
+        closed.realized_pnl = 20.0;
+        let local = PnlBreakdown::local(
+            &[held("AAPL", 265598, 10, 150.0, 160.0), closed],
+            &HashMap::new(),
+        );
+        assert_eq!(local.unrealized, 100.0);
+        assert_eq!(local.realized, 20.0);
+        assert_eq!(local.daily, 120.0);
+
+        let lines = ui::pnl_lines(Some(&ib), &local);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].text.trim_start().starts_with("IB"));
+        assert!(lines[2].text.contains("$20.00"));
+        assert!(ui::pnl_lines(None, &local).is_empty());
+    }
+
+    #[test]
+    fn test_matching_books() {
+        let trades = [held("AAPL", 265598, 10, 150.0, 160.0)];
+        let ib = PnlBreakdown {
+            daily: 100.5,
+            unrealized: 100.5,
+            realized: 0.0,
+        };
+        let positions = HashMap::from([(265598, ib_position(10.0, 99.5))]);
+        assert!(
+            pnl::discrepancies(Some(&ib), &positions, &trades, &HashMap::new(), 1.0).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_missed_fill() {
+        // IB filled another 10 shares we never saw
+        let trades = [held("AAPL", 265598, 10, 150.0, 160.0)];
+        let ib = PnlBreakdown {
+            daily: 200.0,
+            unrealized: 200.0,
+            realized: 0.0,
+        };
+        let positions = HashMap::from([(265598, ib_position(20.0, 200.0))]);
+
+        let found = pnl::discrepancies(Some(&ib), &positions, &trades, &HashMap::new(), 1.0);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].key(), "Account unrealized");
+        assert_eq!(
+            found[0].to_string(),
+            "Account unrealized PnL: IB $200.00 vs ours $100.00"
+        );
+        assert_eq!(
+            found[1],
+            Discrepancy::Position {
+                symbol: "AAPL".to_string(),
+                ib: 20.0,
+                ours: 10,
+            }
+        );
+        assert_eq!(found[1].key(), "AAPL position");
+    }
+
+    #[test]
+    fn test_position_pnl_mismatch() {
+        let trades = [
+            held("AAPL", 265598, 10, 150.0, 160.0),
+            // Not streamed yet, so not compared
+            held("MSFT", 272093, 5, 400.0, 410.0),
+        ];
+        let positions = HashMap::from([(265598, ib_position(10.0, 70.0))]);
+
+        let found = pnl::discrepancies(None, &positions, &trades, &HashMap::new(), 1.0);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].key(), "AAPL unrealized");
+        assert_eq!(
+            found[0].to_string(),
+            "AAPL unrealized PnL: IB $70.00 vs ours $100.00"
+        );
+    }
+
+    fn fill(side: &str, commission: f64) -> Fill {
+        Fill {
+            execution_id: format!("{}-{}", side, commission),
+            order_id: 1,
+            side: side.to_string(),
+            shares: 10.0,
+            price: 150.0,
+            commission: Some(commission),
+            filled_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_local_pnl_is_net_of_commissions() {
+        let mut aapl = held("AAPL", 265598, 10, 150.0, 160.0);
+        aapl.journal_id = Some(1);
+        let mut msft = Trade::new("MSFT".to_string());
+        msft.journal_id = Some(2);
+        msft.stage = Stage::Close;
+        msft.realized_pnl = 20.0;
+        let commissions = HashMap::from([
+            (1, Commissions::of(&aapl, &[fill("BOT", 1.0)])),
+            (
+                2,
+                Commissions::of(&msft, &[fill("BOT", 1.0), fill("SLD", 1.5)]),
+            ),
+        ]);
+        assert_eq!(commissions[&1].unrealized, 1.0);
+        assert_eq!(commissions[&2].realized, 2.5);
+
+        let trades = [aapl, msft];
+        let local = PnlBreakdown::local(&trades, &commissions);
+        assert_eq!(local.unrealized, 99.0);
+        assert_eq!(local.realized, 17.5);
+        assert_eq!(local.daily, 116.5);
+
+        // IB's figures are net, so they match once commissions come off ours
+        let ib = PnlBreakdown {
+            daily: 116.5,
+            unrealized: 99.0,
+            realized: 17.5,
+        };
+        let positions = HashMap::from([(265598, ib_position(10.0, 99.0))]);
+        assert!(pnl::discrepancies(Some(&ib), &positions, &trades, &commissions, 0.1).is_empty());
+        let found = pnl::discrepancies(Some(&ib), &positions, &trades, &HashMap::new(), 0.1);
+        assert_eq!(found.len(), 3);
+    }
+}
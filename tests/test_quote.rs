@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod quote_tests {
+    use ibapi::contracts::tick_types::TickType;
+    use ibapi::market_data::realtime::{TickPrice, TickSize, TickTypes};
+    use ibxrust::quote::{Quote, QuoteRow};
+
+    fn price(tick_type: TickType, price: f64) -> TickTypes {
+        TickTypes::Price(TickPrice {
+            tick_type,
+            price,
+            ..Default::default()
+        })
+    }
+
+    fn size(tick_type: TickType, size: f64) -> TickTypes {
+        TickTypes::Size(TickSize { tick_type, size })
+    }
+
+    #[test]
+    fn test_snapshot_ticks() {
+        let mut quote = Quote::new("AAPL".to_string());
+        quote.apply(&price(TickType::Bid, 149.95));
+        quote.apply(&price(TickType::Ask, 150.05));
+        quote.apply(&price(TickType::Last, 150.0));
+        quote.apply(&price(TickType::Close, 148.0));
+        quote.apply(&size(TickType::Volume, 1_250_000.0));
+        quote.apply(&size(TickType::BidSize, 300.0));
+
+        assert_eq!(quote.bid, Some(149.95));
+        assert_eq!(quote.ask, Some(150.05));
+        assert_eq!(quote.last, Some(150.0));
+        assert_eq!(quote.close, Some(148.0));
+        assert_eq!(quote.volume, Some(1_250_000.0));
+        assert_eq!(quote.change(), Some(2.0));
+        assert!((quote.change_percent().unwrap() - 1.3514).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_delayed_ticks_and_missing_last() {
+        let mut quote = Quote::new("MSFT".to_string());
+        quote.apply(&price(TickType::DelayedClose, 410.0));
+        quote.apply(&price(TickType::DelayedBid, -1.0));
+        quote.apply(&size(TickType::DelayedVolume, 0.0));
+
+        assert_eq!(quote.bid, None);
+        assert_eq!(quote.volume, None);
+        assert_eq!(quote.price(), Some(410.0));
+        assert_eq!(quote.change(), None);
+
+        quote.apply(&price(TickType::DelayedLast, 405.0));
+        assert_eq!(quote.price(), Some(405.0));
+        assert_eq!(quote.change(), Some(-5.0));
+    }
+
+    #[test]
+    fn test_failed_row_json() {
+        let row = QuoteRow::Failed {
+            symbol: "ZZZZ".to_string(),
+            error: "No contract details for ZZZZ".to_string(),
+        };
+        let value = serde_json::to_value(&row).unwrap();
+        assert_eq!(value["symbol"], "ZZZZ");
+        assert_eq!(value["error"], "No contract details for ZZZZ");
+    }
+}
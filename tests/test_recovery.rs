@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod recovery_tests {
+    use chrono::Utc;
+    use ibapi::accounts::Position;
+    use ibapi::contracts::Contract;
+    use ibapi::orders::{order_builder, Action, OrderData};
+    use ibxrust::journal::TradeRecord;
+    use ibxrust::recovery::reconcile;
+    use ibxrust::trade::Stage;
+
+    fn contract(symbol: &str, contract_id: i32) -> Contract {
+        Contract {
+            symbol: symbol.to_string(),
+            contract_id,
+            ..Default::default()
+        }
+    }
+
+    /// A trade the journal left in `Stage::Hold` when the app crashed
+    fn create_unfinished_record(id: i64, symbol: &str, contract_id: i64) -> TradeRecord {
+        TradeRecord {
+            id,
+            symbol: symbol.to_string(),
+            contract_id,
+            account: None,
+            strategy: None,
+            shares: 100,
+            entry_price: 150.0,
+            exit_price: None,
+            stop_price: Some(145.0),
+            realized_pnl: None,
+            stage: Stage::Hold,
+            opened_at: Utc::now(),
+            closed_at: None,
+        }
+    }
+
+    fn position(symbol: &str, contract_id: i32, shares: f64) -> Position {
+        Position {
+            account: "DU123456".to_string(),
+            contract: contract(symbol, contract_id),
+            position: shares,
+            average_cost: 150.1,
+        }
+    }
+
+    fn stop_order(symbol: &str, contract_id: i32, order_id: i32, stop_price: f64) -> OrderData {
+        OrderData {
+            order_id,
+            contract: contract(symbol, contract_id),
+            order: order_builder::stop(Action::Sell, 100.0, stop_price),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_restores_held_trade_with_stop() {
+        let records = vec![create_unfinished_record(1, "AAPL", 265598)];
+        let positions = vec![position("AAPL", 265598, 100.0)];
+        let orders = vec![stop_order("AAPL", 265598, 42, 146.0)];
+
+        let recovery = reconcile(&records, &positions, &orders);
+        assert!(recovery.issues.is_empty(), "{:?}", recovery.issues);
+        assert_eq!(recovery.trades.len(), 1);
+
+        let trade = &recovery.trades[0];
+        assert_eq!(trade.stage, Stage::Hold);
+        assert_eq!(trade.position, 100);
+        assert_eq!(trade.entry_price, 150.0);
+        assert_eq!(trade.journal_id, Some(1));
+        assert_eq!(trade.stop_order_id, Some(42));
+    }
+
+    #[test]
+    fn test_missing_position_marks_trade_closed() {
+        let records = vec![create_unfinished_record(1, "AAPL", 265598)];
+
+        let recovery = reconcile(&records, &[], &[]);
+        assert!(recovery.trades.is_empty());
+        assert_eq!(recovery.closed.len(), 1);
+        assert_eq!(recovery.closed[0].stage, Stage::Close);
+        assert_eq!(recovery.issues.len(), 1);
+    }
+
+    #[test]
+    fn test_reports_discrepancies() {
+        let records = vec![create_unfinished_record(1, "AAPL", 265598)];
+        let positions = vec![
+            position("AAPL", 265598, 60.0),
+            position("MSFT", 272093, 10.0),
+        ];
+
+        let recovery = reconcile(&records, &positions, &[]);
+        assert_eq!(recovery.trades[0].position, 60);
+        // share mismatch, missing stop, untracked MSFT position
+        assert_eq!(recovery.issues.len(), 3, "{:?}", recovery.issues);
+    }
+
+    #[test]
+    fn test_disconnected_trades_are_restored() {
+        // Left open at a clean shutdown: one held position, one unfilled entry
+        let mut held = create_unfinished_record(1, "AAPL", 265598);
+        held.stage = Stage::Disconnect;
+        let mut entry = create_unfinished_record(2, "MSFT", 272093);
+        entry.stage = Stage::Disconnect;
+        entry.shares = 0;
+
+        let positions = vec![position("AAPL", 265598, 100.0)];
+        let orders = vec![OrderData {
+            order_id: 7,
+            contract: contract("MSFT", 272093),
+            order: order_builder::limit_order(Action::Buy, 10.0, 400.0),
+            ..Default::default()
+        }];
+
+        let recovery = reconcile(&[held, entry], &positions, &orders);
+        assert!(recovery.closed.is_empty());
+        assert_eq!(recovery.trades[0].stage, Stage::Hold);
+        assert_eq!(recovery.trades[1].stage, Stage::Open);
+        // The entry order is followed again once the app restarts
+        assert_eq!(recovery.trades[1].entry_order_id, Some(7));
+        assert_eq!(recovery.trades[0].entry_order_id, None);
+    }
+
+    #[test]
+    fn test_positions_are_scoped_by_account() {
+        let mut record = create_unfinished_record(1, "AAPL", 265598);
+        record.account = Some("DU654321".to_string());
+        let mut other = position("AAPL", 265598, 100.0);
+        other.account = "DU999999".to_string();
+        let mut own = position("AAPL", 265598, 40.0);
+        own.account = "DU654321".to_string();
+
+        let recovery = reconcile(&[record.clone()], &[other.clone(), own], &[]);
+        let trade = &recovery.trades[0];
+        assert_eq!(trade.position, 40);
+        assert_eq!(trade.account.as_deref(), Some("DU654321"));
+        assert!(recovery
+            .issues
+            .iter()
+            .any(|issue| issue.contains("in DU999999 is not tracked")));
+
+        // The other account's position doesn't keep the trade open
+        let recovery = reconcile(&[record], &[other], &[]);
+        assert!(recovery.trades.is_empty());
+        assert_eq!(recovery.closed.len(), 1);
+    }
+}
@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod risk_tests {
+    use ibapi::orders::Action;
+    use ibxrust::risk::{OrderCheck, RiskEngine, RiskLimits};
+    use ibxrust::Error;
+    use std::time::{Duration, Instant};
+
+    fn limits() -> RiskLimits {
+        RiskLimits {
+            max_shares_per_order: 500,
+            max_notional_per_order: 20_000.0,
+            max_position_per_symbol: 600,
+            max_price_deviation_pct: 2.0,
+            max_orders_per_minute: 3,
+        }
+    }
+
+    fn buy(shares: i32, limit_price: Option<f64>) -> OrderCheck<'static> {
+        OrderCheck {
+            symbol: "AAPL",
+            action: Action::Buy,
+            shares,
+            limit_price,
+            last_price: 100.0,
+            current_position: 0,
+        }
+    }
+
+    fn assert_risk_error(result: ibxrust::Result<()>, reason: &str) {
+        match result {
+            Err(Error::Risk(message)) => assert!(message.contains(reason), "unexpected reason: {}", message),
+            other => panic!("expected risk error containing {:?}, got {:?}", reason, other),
+        }
+    }
+
+    #[test]
+    fn test_accepts_order_within_limits() {
+        let mut engine = RiskEngine::new(limits());
+        assert!(engine.check(&buy(100, Some(100.5))).is_ok());
+        assert!(engine.check(&buy(100, None)).is_ok());
+    }
+
+    #[test]
+    fn test_max_shares_and_notional() {
+        let mut engine = RiskEngine::new(limits());
+        assert_risk_error(engine.check(&buy(501, None)), "shares per order");
+        assert_risk_error(engine.check(&buy(250, None)), "notional");
+    }
+
+    #[test]
+    fn test_max_position_per_symbol() {
+        let mut engine = RiskEngine::new(limits());
+        let mut order = buy(150, None);
+        order.current_position = 500;
+        assert_risk_error(engine.check(&order), "per symbol");
+
+        // Selling down an oversized position is allowed
+        order.action = Action::Sell;
+        assert!(engine.check(&order).is_ok());
+    }
+
+    #[test]
+    fn test_fat_finger_check() {
+        let mut engine = RiskEngine::new(limits());
+        assert_risk_error(engine.check(&buy(10, Some(110.0))), "from last");
+
+        let mut order = buy(10, Some(100.0));
+        order.last_price = 0.0;
+        assert_risk_error(engine.check(&order), "last price");
+    }
+
+    #[test]
+    fn test_orders_per_minute() {
+        let mut engine = RiskEngine::new(limits());
+        let start = Instant::now();
+        for i in 0..3 {
+            assert!(engine.check_at(&buy(10, None), start + Duration::from_secs(i)).is_ok());
+            engine.record_sent_at(start + Duration::from_secs(i));
+        }
+        assert_risk_error(engine.check_at(&buy(10, None), start + Duration::from_secs(30)), "rate limit");
+
+        // Rejected orders do not count, and the window slides forward
+        assert!(engine.check_at(&buy(10, None), start + Duration::from_secs(60)).is_ok());
+    }
+
+    #[test]
+    fn test_unsent_orders_keep_rate_budget() {
+        let mut engine = RiskEngine::new(limits());
+        let start = Instant::now();
+        // Checked but never placed, e.g. blocked outside regular hours
+        for i in 0..5 {
+            assert!(engine.check_at(&buy(10, None), start + Duration::from_secs(i)).is_ok());
+        }
+        engine.record_sent_at(start);
+        assert!(engine.check_at(&buy(10, None), start + Duration::from_secs(10)).is_ok());
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod schema_tests {
+    use ibxrust::schema::{self, Envelope, SCHEMA_VERSION};
+    use ibxrust::sizing::SizingMode;
+    use ibxrust::trade::{Stage, Trade};
+    use serde_json::Value;
+
+    /// A held AAPL position with a stop and a sizing override
+    fn create_held_trade() -> Trade {
+        let mut trade = Trade::new("AAPL".to_string());
+        trade.create_contract();
+        trade.sizing = Some(SizingMode::PercentOfNetLiq(2.0));
+        trade.stop_price = Some(145.0);
+        trade.open_position(100, 150.0);
+        trade.update_price(152.5);
+        trade
+    }
+
+    #[test]
+    fn test_trade_json_round_trip() {
+        let trade = create_held_trade();
+        let json = schema::to_json(&trade).unwrap();
+
+        let envelope: Envelope<Trade> = serde_json::from_str(&json).unwrap();
+        assert_eq!(envelope.schema_version, SCHEMA_VERSION);
+
+        let restored = envelope.data;
+        assert_eq!(restored.symbol, "AAPL");
+        assert_eq!(restored.stage, Stage::Hold);
+        assert_eq!(restored.position, 100);
+        assert_eq!(restored.sizing, Some(SizingMode::PercentOfNetLiq(2.0)));
+        assert_eq!(restored.opened_at, trade.opened_at);
+        assert_eq!(restored.calculate_pnl(), 250.0);
+        assert_eq!(restored.contract.unwrap().symbol, "AAPL");
+    }
+
+    #[test]
+    fn test_stable_field_names() {
+        let value: Value =
+            serde_json::from_str(&schema::to_json(&create_held_trade()).unwrap()).unwrap();
+        let data = &value["data"];
+
+        assert_eq!(value["schema_version"], 2);
+        assert_eq!(data["stage"], "hold");
+        assert_eq!(data["sizing"], "2%");
+        assert_eq!(data["contract"]["symbol"], "AAPL");
+        assert_eq!(data["contract"]["security_type"], "STK");
+        assert!(data.get("schedule").is_none());
+    }
+}
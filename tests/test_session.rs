@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod session_tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use ibapi::contracts::ContractDetails;
+    use ibxrust::session::{parse_hours, MarketSession, OutsideRthPolicy, TradingSchedule};
+
+    /// Contract details as TWS reports them for a US stock
+    fn create_mock_details() -> ContractDetails {
+        ContractDetails {
+            time_zone_id: "US/Eastern".to_string(),
+            trading_hours: vec![
+                "20240304:0400-20240304:2000".to_string(),
+                "20240305:0400-20240305:2000".to_string(),
+                "20240309:CLOSED".to_string(),
+            ],
+            liquid_hours: vec![
+                "20240304:0930-20240304:1600".to_string(),
+                "20240305:0930-20240305:1600".to_string(),
+                "20240309:CLOSED".to_string(),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_hours_formats() {
+        let ranges = parse_hours(&["20240304:0400-20240304:2000;20240305:CLOSED".to_string()]).unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(
+            ranges[0].0,
+            NaiveDate::from_ymd_opt(2024, 3, 4).unwrap().and_hms_opt(4, 0, 0).unwrap()
+        );
+
+        let legacy = parse_hours(&["20090507:0700-1830,1830-2330".to_string()]).unwrap();
+        assert_eq!(legacy.len(), 2);
+        assert_eq!(
+            legacy[1].1,
+            NaiveDate::from_ymd_opt(2009, 5, 7).unwrap().and_hms_opt(23, 30, 0).unwrap()
+        );
+
+        assert!(parse_hours(&["20240304:0400".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_session_classification() {
+        let schedule = TradingSchedule::from_details(&create_mock_details()).unwrap();
+        // March 4th 2024 is before DST, so Eastern time is UTC-5
+        let at = |hour, minute| Utc.with_ymd_and_hms(2024, 3, 4, hour, minute, 0).unwrap();
+
+        assert_eq!(schedule.session_at(at(8, 59)), MarketSession::Closed);
+        assert_eq!(schedule.session_at(at(9, 0)), MarketSession::PreMarket);
+        assert_eq!(schedule.session_at(at(14, 30)), MarketSession::Regular);
+        assert_eq!(schedule.session_at(at(20, 59)), MarketSession::Regular);
+        assert_eq!(schedule.session_at(at(21, 0)), MarketSession::AfterHours);
+        assert_eq!(schedule.session_at(at(23, 30)), MarketSession::AfterHours);
+        assert_eq!(
+            schedule.session_at(Utc.with_ymd_and_hms(2024, 3, 9, 15, 0, 0).unwrap()),
+            MarketSession::Closed
+        );
+    }
+
+    #[test]
+    fn test_regular_session_start() {
+        let schedule = TradingSchedule::from_details(&create_mock_details()).unwrap();
+        let open = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap().and_hms_opt(9, 30, 0).unwrap();
+
+        assert_eq!(schedule.regular_session_start(Utc.with_ymd_and_hms(2024, 3, 4, 14, 0, 0).unwrap()), None);
+        assert_eq!(schedule.regular_session_start(Utc.with_ymd_and_hms(2024, 3, 4, 14, 30, 0).unwrap()), Some(open(4)));
+        assert_eq!(schedule.regular_session_start(Utc.with_ymd_and_hms(2024, 3, 4, 20, 0, 0).unwrap()), Some(open(4)));
+        // The next day's open starts a new session
+        assert_eq!(schedule.regular_session_start(Utc.with_ymd_and_hms(2024, 3, 5, 15, 0, 0).unwrap()), Some(open(5)));
+    }
+
+    #[test]
+    fn test_unknown_time_zone() {
+        let mut details = create_mock_details();
+        details.time_zone_id = "Mars/Olympus".to_string();
+        assert!(TradingSchedule::from_details(&details).is_err());
+    }
+
+    #[test]
+    fn test_market_order_policy_fails_closed() {
+        let block = OutsideRthPolicy::Block;
+        assert!(block.check_market_order("AAPL", Some(MarketSession::Regular)).is_ok());
+        assert!(block.check_market_order("AAPL", Some(MarketSession::PreMarket)).is_err());
+        // No schedule means the session is unknown, which is not regular hours
+        let error = block.check_market_order("AAPL", None).unwrap_err();
+        assert!(error.to_string().contains("trading hours unknown"), "{}", error);
+
+        let warn = OutsideRthPolicy::Warn;
+        assert!(warn.check_market_order("AAPL", Some(MarketSession::AfterHours)).is_ok());
+        assert!(warn.check_market_order("AAPL", None).is_ok());
+    }
+}
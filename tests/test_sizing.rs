@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod sizing_tests {
+    use ibxrust::sizing::SizingMode;
+    use ibxrust::trade::Trade;
+
+    #[test]
+    fn test_parse_sizing_modes() {
+        assert_eq!("100".parse::<SizingMode>().unwrap(), SizingMode::FixedShares(100));
+        assert_eq!("$5,000".parse::<SizingMode>().unwrap(), SizingMode::FixedDollars(5000.0));
+        assert_eq!("2.5%".parse::<SizingMode>().unwrap(), SizingMode::PercentOfNetLiq(2.5));
+        assert_eq!("risk:$200".parse::<SizingMode>().unwrap(), SizingMode::RiskBudget(200.0));
+        assert_eq!("risk:150".parse::<SizingMode>().unwrap(), SizingMode::RiskBudget(150.0));
+
+        assert!("".parse::<SizingMode>().is_err());
+        assert!("-10".parse::<SizingMode>().is_err());
+        assert!("$abc".parse::<SizingMode>().is_err());
+        assert!("0%".parse::<SizingMode>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for text in ["100", "$5000", "2%", "risk:$200"] {
+            let mode = text.parse::<SizingMode>().unwrap();
+            assert_eq!(mode.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_share_calculation() {
+        assert_eq!(SizingMode::FixedShares(25).shares(150.0, None, None).unwrap(), 25);
+        assert_eq!(SizingMode::FixedDollars(5000.0).shares(150.0, None, None).unwrap(), 33);
+        assert_eq!(
+            SizingMode::PercentOfNetLiq(10.0).shares(150.0, None, Some(100_000.0)).unwrap(),
+            66
+        );
+        // $200 at risk with a $2.50 stop distance
+        assert_eq!(SizingMode::RiskBudget(200.0).shares(150.0, Some(147.5), None).unwrap(), 80);
+    }
+
+    #[test]
+    fn test_share_calculation_errors() {
+        assert!(SizingMode::PercentOfNetLiq(10.0).shares(150.0, None, None).is_err());
+        assert!(SizingMode::RiskBudget(200.0).shares(150.0, None, None).is_err());
+        assert!(SizingMode::RiskBudget(200.0).shares(150.0, Some(150.0), None).is_err());
+        assert!(SizingMode::FixedDollars(100.0).shares(150.0, None, None).is_err());
+        assert!(SizingMode::FixedDollars(5000.0).shares(0.0, None, None).is_err());
+    }
+
+    #[test]
+    fn test_trade_override() {
+        let mut trade = Trade::new("AAPL".to_string());
+        trade.update_price(100.0);
+        let default_mode = SizingMode::FixedShares(10);
+
+        assert_eq!(trade.shares_to_buy(&default_mode, None).unwrap(), 10);
+
+        trade.sizing = Some(SizingMode::FixedDollars(2500.0));
+        assert_eq!(trade.shares_to_buy(&default_mode, None).unwrap(), 25);
+
+        trade.sizing = Some(SizingMode::RiskBudget(100.0));
+        trade.stop_price = Some(98.0);
+        assert_eq!(trade.shares_to_buy(&default_mode, None).unwrap(), 50);
+    }
+
+    #[test]
+    fn test_trade_override_from_prompt() {
+        let mut trade = Trade::new("AAPL".to_string());
+        trade.update_price(100.0);
+        let default_mode = SizingMode::FixedShares(10);
+
+        trade.set_sizing(" $2,000 ").unwrap();
+        assert_eq!(trade.sizing, Some(SizingMode::FixedDollars(2000.0)));
+        assert_eq!(trade.shares_to_buy(&default_mode, None).unwrap(), 20);
+
+        // A bad answer keeps the override already set
+        assert!(trade.set_sizing("lots").is_err());
+        assert_eq!(trade.sizing, Some(SizingMode::FixedDollars(2000.0)));
+
+        trade.set_sizing("").unwrap();
+        assert_eq!(trade.sizing, None);
+        assert_eq!(trade.shares_to_buy(&default_mode, None).unwrap(), 10);
+    }
+
+    #[test]
+    fn test_risk_sizing_needs_atr_stop() {
+        assert!(SizingMode::RiskBudget(200.0).check_stop_source(None).is_err());
+        assert!(SizingMode::RiskBudget(200.0).check_stop_source(Some(2.0)).is_ok());
+        assert!(SizingMode::FixedShares(100).check_stop_source(None).is_ok());
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod ui_tests {
+    use crossterm::style::Color;
+    use ibxrust::trade::Trade;
+    use ibxrust::ui::{self, Dashboard, WatchRow, ORANGE};
+
+    #[test]
+    fn test_header_lines() {
+        let profit = ui::pnl_line(80.0);
+        assert_eq!(profit.text, "*** PnL: $80.00");
+        assert_eq!(profit.color, Some(Color::Green));
+
+        let loss = ui::pnl_line(-12.5);
+        assert_eq!(loss.text, "*** PnL: -$12.50");
+        assert_eq!(loss.color, Some(Color::Red));
+
+        let price = ui::price_line("AAPL", 160.0);
+        assert_eq!(price.text, "*** AAPL : $160.00");
+        assert_eq!(price.color, Some(ORANGE));
+    }
+
+    #[test]
+    fn test_prompt_follows_position() {
+        let mut trade = Trade::new("AAPL".to_string());
+        assert_eq!(ui::prompt_line(&trade, false).text, ">> Buy (y/n) ?");
+
+        trade.open_position(100, 150.0);
+        assert_eq!(ui::prompt_line(&trade, false).text, ">> Sell (y/n) ?");
+        assert_eq!(ui::prompt_line(&trade, true).text, ">> Order working...");
+
+        trade.close_position();
+        assert_eq!(ui::prompt_line(&trade, false).text, ">> Buy (y/n) ?");
+    }
+
+    #[test]
+    fn test_frame_layout() {
+        let mut held = Trade::new("AAPL".to_string());
+        held.open_position(100, 150.0);
+        held.update_price(149.0);
+        let mut watched = Trade::new("MSFT".to_string());
+        watched.update_price(410.0);
+        let rows = vec![
+            WatchRow {
+                trade: &held,
+                order_working: false,
+                vwap: None,
+                atr: None,
+            },
+            WatchRow {
+                trade: &watched,
+                order_working: false,
+                vwap: None,
+                atr: None,
+            },
+        ];
+
+        let mut dashboard = Dashboard::new();
+        for i in 0..20 {
+            dashboard.message(format!("message {}", i));
+        }
+        let frame = dashboard.frame(&rows, 1, -100.0);
+        assert_eq!(frame[0].text, "*** PnL: -$100.00");
+        assert_eq!(frame[1].text, "*** MSFT : $410.00");
+        assert_eq!(frame[2].text, ">> Buy (y/n) ?");
+        assert!(frame[5].text.starts_with("  AAPL"));
+        assert_eq!(frame[5].color, Some(Color::Red));
+        assert!(frame[6].text.starts_with("> MSFT"));
+        // Only the most recent messages are kept
+        assert_eq!(frame.last().unwrap().text, "message 19");
+        assert!(dashboard.messages().count() < 20);
+    }
+}